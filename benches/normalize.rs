@@ -0,0 +1,45 @@
+//! Times normalizing words, against always running them through NFKC,
+//! and training on ASCII and on accented text.
+//!
+//!     $ cargo bench --bench normalize
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use papagaio::{normalize, Normalization, Stats, TrainConfig};
+use unicode_normalization::UnicodeNormalization;
+
+const ASCII: &[&str] = &["The", "quick", "brown", "fox", "jumps", "over", "the", "lazy", "dog"];
+const ACCENTED: &[&str] = &["São", "Paulo", "não", "é", "uma", "cidade", "pequena", "coração", "Ação"];
+
+fn main() {
+    let normalization = Normalization::default();
+    for (name, words) in [("ascii", ASCII), ("accented", ACCENTED)] {
+        let words: Vec<&str> = words.iter().copied().cycle().take(1_000_000).collect();
+        let quick = time(|| for word in &words {
+            black_box(normalize(word, &normalization));
+        });
+        let full = time(|| for word in &words {
+            black_box(word.nfkc().collect::<String>().to_lowercase());
+        });
+        println!("normalize {}: {:?}, always nfkc: {:?}", name, quick, full);
+
+        let lines: Vec<String> = words.chunks(10).map(|line| line.join(" ")).collect();
+        let train = time(|| {
+            black_box(Stats::train(&lines, &TrainConfig::default()));
+        });
+        println!("train {}: {:?}", name, train);
+    }
+}
+
+// the fastest of a few runs
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
@@ -0,0 +1,30 @@
+#ifndef PAPAGAIO_H
+#define PAPAGAIO_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A trained model, only ever handled through pointers. */
+typedef struct papagaio_model papagaio_model;
+
+/* Trains a model on every line of text, or returns NULL when text is
+ * NULL or not UTF-8. Free it with papagaio_free. */
+papagaio_model *papagaio_train(const char *text);
+
+/* Makes up a sentence of at most words words, the same one for the same
+ * seed, or returns NULL when model is NULL or memory runs out. Free it
+ * with free. */
+char *papagaio_generate(const papagaio_model *model, size_t words, uint64_t seed);
+
+/* Frees a model, doing nothing on NULL. */
+void papagaio_free(papagaio_model *model);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,79 @@
+//! A C interface to train and generate, see `include/papagaio.h`.
+//!
+//! Models returned by [`papagaio_train`] belong to the caller, who frees
+//! them with [`papagaio_free`] once done. Strings returned by
+//! [`papagaio_generate`] belong to the caller as well, and are allocated
+//! with `malloc`, so they are freed with `free` like any other.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr;
+
+use crate::{GeneratorConfig, Stats, TrainConfig};
+
+/// Trains a model with the default settings on every line of `text`.
+///
+/// Returns null when `text` is null or not UTF-8.
+///
+/// # Safety
+///
+/// `text` must be null or a valid, nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn papagaio_train(text: *const c_char) -> *mut Stats {
+    if text.is_null() {
+        return ptr::null_mut();
+    }
+    match CStr::from_ptr(text).to_str() {
+        Ok(text) => Box::into_raw(Box::new(Stats::train(text.lines(), &TrainConfig::default()))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Makes up a sentence of at most `words` words, the same one for the
+/// same `seed`.
+///
+/// Returns null when `model` is null, or when there is no memory left
+/// for the sentence.
+///
+/// # Safety
+///
+/// `model` must be null or returned by [`papagaio_train`], and not freed
+/// yet.
+#[no_mangle]
+pub unsafe extern "C" fn papagaio_generate(model: *const Stats, words: usize, seed: u64) -> *mut c_char {
+    let stats = match model.as_ref() {
+        Some(stats) => stats,
+        None => return ptr::null_mut(),
+    };
+    let config = GeneratorConfig {
+        seed: Some(seed),
+        max_words: Some(words),
+        ..GeneratorConfig::default()
+    };
+    // without a start word, generating cannot fail
+    let sentence = stats
+        .generate(&config)
+        .map(|words| words.collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+    // words are split on whitespace, so they hold no nul either
+    let s = libc::malloc(sentence.len() + 1) as *mut u8;
+    if s.is_null() {
+        return ptr::null_mut();
+    }
+    ptr::copy_nonoverlapping(sentence.as_ptr(), s, sentence.len());
+    *s.add(sentence.len()) = 0;
+    s as *mut c_char
+}
+
+/// Frees a model returned by [`papagaio_train`], doing nothing on null.
+///
+/// # Safety
+///
+/// `model` must be null or returned by [`papagaio_train`], and not freed
+/// yet.
+#[no_mangle]
+pub unsafe extern "C" fn papagaio_free(model: *mut Stats) {
+    if !model.is_null() {
+        drop(Box::from_raw(model));
+    }
+}
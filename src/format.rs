@@ -0,0 +1,125 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead, Write};
+
+use crate::{Stats, TrainConfig};
+
+/// Writes the transition graph of `stats` as a Graphviz digraph.
+///
+/// Each context is a node, and each edge is labeled with the number of
+/// times it was seen during training.
+pub fn dot<W: Write>(mut writer: W, stats: &Stats) -> io::Result<()> {
+    let mut contexts: Vec<_> = stats.contexts().collect();
+    contexts.sort_by_key(|&(context, _)| context);
+
+    writeln!(writer, "digraph papagaio {{")?;
+    for (context, next) in contexts {
+        let mut next: Vec<_> = next.collect();
+        next.sort();
+        for (neigh, number) in next {
+            writeln!(
+                writer,
+                "    \"{}\" -> \"{}\" [label={}];",
+                escape_dot(context),
+                escape_dot(&stats.advance(context, neigh)),
+                number,
+            )?;
+        }
+    }
+    writeln!(writer, "}}")?;
+    writer.flush()
+}
+
+/// Writes the raw transition counts of `stats` as JSON.
+pub fn json<W: Write>(mut writer: W, stats: &Stats) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut writer, stats)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// Writes one `from,to,count` row per transition of `stats`, after a
+/// header row.
+///
+/// Fields are quoted when they need to be, following RFC 4180.
+pub fn csv<W: Write>(mut writer: W, stats: &Stats) -> io::Result<()> {
+    let mut contexts: Vec<_> = stats.contexts().collect();
+    contexts.sort_by_key(|&(context, _)| context);
+
+    writeln!(writer, "from,to,count")?;
+    for (context, next) in contexts {
+        let mut next: Vec<_> = next.collect();
+        next.sort();
+        for (neigh, number) in next {
+            writeln!(writer, "{},{},{}", escape_csv(context), escape_csv(neigh), number)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Builds a model from the `from,to,count` rows of `reader`, as written
+/// by [`csv`], trained as though with `config`.
+///
+/// A header row is skipped, and every `from` context must be made of
+/// `order` words. Fails on the first malformed row, telling its line.
+pub fn read_csv<R: BufRead>(reader: R, config: &TrainConfig) -> io::Result<Stats> {
+    let mut stats = Stats::with_config(config);
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let malformed = |e: String| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, e));
+        if line.trim().is_empty() || (i == 0 && line == "from,to,count") {
+            continue;
+        }
+        let (from, to, count) = match &split_csv(&line).map_err(malformed)?[..] {
+            [from, to, count] => (from.clone(), to.clone(), count.clone()),
+            fields => return Err(malformed(format!("expected 3 fields, found {}", fields.len()))),
+        };
+        let count: i32 = count
+            .trim()
+            .parse()
+            .map_err(|e| malformed(format!("invalid count '{}': {}", count, e)))?;
+        if count <= 0 {
+            return Err(malformed(format!("count must be greater than 0, found {}", count)));
+        }
+        if from.split(' ').count() != stats.order {
+            return Err(malformed(format!("context '{}' is not made of {} words", from, stats.order)));
+        }
+        stats.add(&from, &to, count);
+    }
+    Ok(stats)
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_csv(s: &str) -> Cow<'_, str> {
+    if s.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", s.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+// splits a row into its fields, unquoting them
+fn split_csv(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            },
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
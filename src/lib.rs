@@ -0,0 +1,57 @@
+//! A Markov chain designed to shitpost.
+//!
+//! Train a model from lines of text, then draw words from it:
+//!
+//! ```
+//! use papagaio::{GeneratorConfig, Sampler, Stats, TrainConfig, Usage};
+//!
+//! let lines = vec![
+//!     "the cat sat on the mat",
+//!     "the dog sat on the cat",
+//! ];
+//! let stats = Stats::train(lines, &TrainConfig::default());
+//!
+//! let config = GeneratorConfig {
+//!     seed: Some(42),
+//!     max_words: Some(10),
+//!     start: Some("the".to_string()),
+//!     ..GeneratorConfig::default()
+//! };
+//! let sampler = Sampler::with_config(&stats, &config);
+//! let words: Vec<&str> = Usage::with_config(&sampler, &config)
+//!     .unwrap()
+//!     .collect();
+//! assert_eq!(words[0], "the");
+//! assert!(words.len() <= 10);
+//! ```
+//!
+//! Or let the model set up the sampler itself, and stop whenever:
+//!
+//! ```
+//! # use papagaio::{GeneratorConfig, Stats, TrainConfig};
+//! # let stats = Stats::train(vec!["the cat sat on the mat"], &TrainConfig::default());
+//! let config = GeneratorConfig::default();
+//! for word in stats.generate(&config).unwrap().take_while(|word| word != "mat") {
+//!     println!("{}", word);
+//! }
+//! ```
+
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod format;
+mod interner;
+pub mod model;
+mod normalization;
+mod sampler;
+mod score;
+mod stats;
+pub mod tokenizer;
+mod usage;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use normalization::{normalize, Form, Normalization};
+pub use stats::{determine_highest_usage, read_stats, read_stats_with, Next, Numbers, Segment, Stat, Stats, Tokens, TrainConfig, UsageCache, END, NUM, START, UNK};
+pub use sampler::{Sampler, Sampling};
+pub use score::{score, Score};
+pub use usage::{GeneratorConfig, Usage};
@@ -1,265 +1,1695 @@
-use std::collections::HashMap;
-use std::io::{self, BufRead, BufReader, Write, BufWriter};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Debug, Display};
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write, BufReader, BufWriter};
+use std::str::FromStr;
 
-use permutation::permutation;
-use unicode_normalization::UnicodeNormalization;
+use flate2::bufread::MultiGzDecoder;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-#[derive(Clone, Debug)]
-struct Stats {
-    of: HashMap<String, Stat>,
-}
-
-#[derive(Clone, Debug)]
-struct Stat {
-    next: HashMap<String, i32>,
-}
-
-#[derive(Clone, Debug)]
-struct Usage<'a> {
-    threshold: f32,
-    current: String,
-    usage: &'a HashMap<String, Vec<String>>,
-}
+use papagaio::model::{self, ModelFormat};
+use papagaio::tokenizer::Separators;
+use papagaio::{determine_highest_usage, format, normalize, read_stats, score, Form, GeneratorConfig, Normalization, Numbers, Sampler, Sampling, Segment, Stats, Tokens, TrainConfig, Usage, END};
 
 struct Flags {
     thres: f32,
+    temperature: f64,
     words: usize,
+    min_words: usize,
+    no_repeat: usize,
+    count: usize,
+    seed: Option<u64>,
+    order: Option<usize>,
+    tokens: Option<Tokens>,
+    reverse: Option<bool>,
+    token_regex: Option<Regex>,
+    form: Option<Form>,
+    lowercase: Option<bool>,
+    min_count: Option<i32>,
+    no_self_loops: bool,
+    numbers: Numbers,
+    stopwords: Option<String>,
+    min_token_len: usize,
+    lossy: bool,
+    strip_html: bool,
+    segment: Segment,
+    split_on: Option<Separators>,
+    vocab: Option<usize>,
+    threads: usize,
+    skip_unknown: bool,
+    verbose: bool,
+    print: Option<Format>,
+    save: Option<String>,
+    model_format: ModelFormat,
+    no_verify: bool,
+    load: Option<String>,
+    import_csv: Option<String>,
+    merge: bool,
+    query: Option<String>,
+    top: Option<(String, usize)>,
+    score: Option<String>,
+    smoothing: Option<f64>,
+    start: Option<String>,
+    delimiter: Option<String>,
+    no_newline: bool,
+    wrap: Option<usize>,
+    paragraph: Option<usize>,
+    prose: bool,
+    output: Option<String>,
+    interactive: bool,
+    restart_on_dead_end: bool,
+    stop_prob: f64,
+    backoff: bool,
+    entropy: bool,
+    cycles: Option<usize>,
+    greedy: bool,
+    summary: bool,
+    print_prefix: Option<String>,
+    show_meta: bool,
+    beam: Option<usize>,
+    max_attempts: Option<usize>,
+    sampling: Sampling,
+    progress: bool,
+    bidirectional: bool,
+    end: Option<String>,
+    corpora: Vec<(String, i32)>,
+    files: Vec<String>,
+}
+
+enum Format {
+    Debug,
+    Dot,
+    Json,
+    Csv,
 }
 
 enum Arguments {
     None,
-    Print,
-    Values(Flags),
+    Help,
+    Version,
+    Values(Box<Flags>),
 }
 
 enum ArgumentKind {
     Flag,
     Words,
+    MinWords,
+    NoRepeat,
+    Count,
     Threshold,
+    Temperature,
+    StopProb,
+    Seed,
+    Order,
+    Normalization,
+    MinCount,
+    Vocab,
+    Threads,
+    Save,
+    Load,
+    ImportCsv,
+    Corpus,
+    ModelFormat,
+    Format,
+    Query,
+    Top,
+    Cycles,
+    Beam,
+    Sampling,
+    MaxAttempts,
+    Score,
+    Smoothing,
+    Start,
+    End,
+    Delimiter,
+    Wrap,
+    Paragraph,
+    Tokenizer,
+    NumberToken,
+    Stopwords,
+    Segment,
+    SplitOn,
+    SplitOnlyOn,
+    MinTokenLen,
+    TokenRegex,
+    Output,
+    PrintPrefix,
 }
 
+const OPTIONS: &[(&str, &str)] = &[
+    ("-h, --help", "print this help and exit"),
+    ("-V, --version", "print the version and exit"),
+    ("-p, --print", "print the words graph instead of generating"),
+    ("--print-prefix <prefix>", "print the words graph only for words starting with prefix, replacing -p <prefix>"),
+    ("-s, --seed <u64>", "seed for reproducible output (default: random)"),
+    ("--show-meta", "print the seed, threshold, words and start of every sentence to stderr"),
+    ("-t, --threshold <f32>", "share of contexts to start from without start tokens (default: 0.75)"),
+    ("--start <word>", "start every sentence with this word"),
+    ("--bidirectional", "also learn sentences backwards, to steer them towards --end"),
+    ("--end <word>", "steer every sentence towards ending with this word"),
+    ("--temperature <f64>", "reshapes word probabilities, must be > 0 (default: 1)"),
+    ("--sampling <mode>", "uniform, softmax, topk:<k> or topp:<p> to draw among the likeliest (default: uniform)"),
+    ("-w, --words <usize>", "most words per sentence, which may end sooner (default: 100)"),
+    ("--max-words <usize>", "same as --words"),
+    ("--min-words <usize>", "least words per sentence, going on past its end (default: 0)"),
+    ("--greedy", "always follow the most frequent word, until a word repeats"),
+    ("--beam <k>", "print the likeliest sentence found keeping the k likeliest at every word"),
+    ("--backoff", "go on from shorter contexts when --order ones were never seen"),
+    ("--max-attempts <n>", "stop sentences after drawing this many words for them, -v warns"),
+    ("--stop-prob <f64>", "chance of ending sentences after each word past --min-words (default: 0)"),
+    ("--restart-on-deadend", "go on from a fresh start when stuck before --min-words"),
+    ("--no-repeat <usize>", "avoid repeating any of the last n words (default: 0)"),
+    ("-n, --count <usize>", "number of sentences (default: 1)"),
+    ("--interactive", "make up a sentence for every start word read from stdin"),
+    ("--delimiter <str>", "put this between words, \\n and \\t included (default: space)"),
+    ("--no-newline", "do not end the last sentence with a newline"),
+    ("--wrap <usize>", "break lines between words before they get this wide"),
+    ("--paragraph <usize>", "join this many sentences into each of the --count paragraphs"),
+    ("--prose", "capitalize sentences and end them with a period"),
+    ("--order <usize>", "words of context per transition (default: 1)"),
+    ("--normalization <form>", "one of nfc, nfkc, nfd, nfkd or none (default: nfkc)"),
+    ("--no-lowercase", "keep the case of words as they are, growing the vocabulary"),
+    ("--preserve-case", "same as --no-lowercase"),
+    ("--char", "make up words from characters instead of sentences"),
+    ("--tokenizer <words|punct>", "split off punctuation into tokens of its own with punct"),
+    ("--split-on <chars>", "split words on these characters as well as whitespace, \\t included"),
+    ("--split-only-on <chars>", "split words on these characters only, instead of whitespace"),
+    ("--token-regex <regex>", "take every match as a word, instead of splitting on whitespace"),
+    ("--reverse", "learn sentences backwards, generating them from their end"),
+    ("--min-count <i32>", "drop transitions seen less than this many times"),
+    ("--number-token <keep|collapse|drop>", "learn numbers as they are, all as <NUM>, or not at all"),
+    ("--stopwords <path>", "leave out the words of a file, one per line, linking the rest"),
+    ("--min-token-len <usize>", "leave out tokens with less characters, linking the rest"),
+    ("--lossy", "replace invalid UTF-8 with U+FFFD instead of failing"),
+    ("--segment <line|sentence>", "learn every line as a sentence, or split sentences after . ! and ?"),
+    ("--strip-html", "remove HTML tags and decode entities like &amp; before training"),
+    ("--no-self-loops", "never learn a word following itself, unlike --no-repeat"),
+    ("--vocab <usize>", "keep only this many words, the rest become <UNK>"),
+    ("--threads <usize>", "train with this many threads, 0 for one per core (default: 1)"),
+    ("--skip-unknown", "never generate <UNK>"),
+    ("-v, --verbose", "report what is going on to stderr"),
+    ("--progress", "report how much of the input was read to stderr while training"),
+    ("-o, --output <path>", "write to a file instead of stdout"),
+    ("--save <path>", "save the trained model to a file"),
+    ("--load <path>", "load a saved model instead of training, not with files or --corpus"),
+    ("--import-csv <path>", "build the model from from,to,count rows, not with files or --corpus"),
+    ("--format-model <json|bincode>", "how to save the model, detected on load (default: json)"),
+    ("--no-verify", "load models without checking their checksum"),
+    ("--merge", "combine the given saved models, each as <path>[:<weight>], counts rounded to at least 1"),
+    ("--corpus <path>[:<i32>]", "train on a file too, its transitions counting this many times"),
+    ("--format <debug|dot|json|csv>", "print the model in the given format"),
+    ("--query <word>", "print what follows a word and how likely it is"),
+    ("--top <word>:<n>", "print the n words most likely to follow a word"),
+    ("--cycles <len>", "print the loops of at most len words and how likely each one is"),
+    ("--summary", "print how many words were learned, and the most frequent ones"),
+    ("--entropy", "print the entropy of what follows every word, in bits, highest first"),
+    ("--score <path>", "print how likely a text is under the model, - for stdin"),
+    ("--smoothing <f64>", "add this to every count, 1 when scoring (default: 0)"),
+];
+
+// short flags that take no value, and those that do
+const SHORT_SWITCHES: &str = "hVpv";
+const SHORT_OPTIONS: &str = "stwno";
+
+// variables standing for flags, which are given before the command line
+// so that the flags given there take precedence over them
+const ENVIRONMENT: &[(&str, &str)] = &[
+    ("PAPAGAIO_THRESHOLD", "-t"),
+    ("PAPAGAIO_WORDS", "-w"),
+    ("PAPAGAIO_SEED", "-s"),
+];
+
 fn main() {
     // fetch arguments
     let mut args = std::env::args();
     let prog_name = args.next().unwrap();
+    let defaults = match environment() {
+        Ok(defaults) => defaults,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        },
+    };
 
     // parse arguments
-    let args = match parse_arguments(args) {
+    let args = match parse_arguments(defaults.into_iter().chain(args)) {
         Ok(args) => args,
         Err(e) => {
             eprintln!("error: {}", e);
-            usage(&prog_name);
-            return;
+            eprintln!("{}", usage(&prog_name));
+            std::process::exit(2);
         },
     };
 
-    // determine highest usage for each entry
-    let stats = read_stats()
-        .expect("failed to read stats");
-    let usage = determine_highest_usage(&stats);
-
     // handle args...
-    let (thres, words) = match args {
-        Arguments::None => (0.75, 100),
-        Arguments::Values(Flags { thres, words }) => (thres, words),
-        Arguments::Print => {
-            println!("{:#?}", usage);
-            return;
+    let result = match args {
+        Arguments::None => run(Flags::default()),
+        Arguments::Values(flags) => run(*flags),
+        Arguments::Help => help(&prog_name).map_err(Into::into),
+        Arguments::Version => version().map_err(Into::into),
+    };
+
+    if let Err(e) = result {
+        // a closed pipe just means nobody wants more gibberish
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            if e.kind() == io::ErrorKind::BrokenPipe {
+                return;
+            }
+        }
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(flags: Flags) -> Result<(), Box<dyn std::error::Error>> {
+    let trains_on_stdin = flags.load.is_none() && flags.import_csv.is_none() && flags.files.is_empty() && flags.corpora.is_empty();
+    if flags.score.as_deref() == Some("-") && trains_on_stdin {
+        return Err("cannot both train on and score stdin".into());
+    }
+    if flags.interactive && trains_on_stdin {
+        return Err("cannot both train on stdin and read start words from it".into());
+    }
+
+    let mut stopwords = HashSet::new();
+    if let Some(path) = &flags.stopwords {
+        for line in open(path)?.lines() {
+            let line = line.map_err(|e| format!("failed to read {}: {}", path, e))?;
+            stopwords.extend(Some(line.trim()).filter(|word| !word.is_empty()).map(str::to_string));
+        }
+    }
+
+    let config = TrainConfig {
+        order: flags.order.unwrap_or(1),
+        tokens: flags.tokens.unwrap_or(Tokens::Words),
+        reverse: flags.reverse.unwrap_or(false),
+        token_regex: flags.token_regex.clone(),
+        normalization: Normalization {
+            form: flags.form.unwrap_or(Form::Nfkc),
+            lowercase: flags.lowercase.unwrap_or(true),
+        },
+        vocab: flags.vocab,
+        threads: flags.threads,
+        weight: 1,
+        no_self_loops: flags.no_self_loops,
+        numbers: flags.numbers,
+        stopwords,
+        min_token_len: flags.min_token_len,
+        lossy: flags.lossy,
+        strip_html: flags.strip_html,
+        segment: flags.segment,
+        split_on: flags.split_on.clone(),
+    };
+
+    if flags.bidirectional != flags.end.is_some() {
+        return Err("--bidirectional and --end must be given together".into());
+    }
+    // saved models are never trained on, only merged with others
+    let trains_on_files = !flags.files.is_empty() && !flags.merge || !flags.corpora.is_empty();
+    if flags.load.is_some() && trains_on_files {
+        return Err("--load cannot be combined with files or --corpus to train on".into());
+    }
+    if flags.import_csv.is_some() && trains_on_files {
+        return Err("--import-csv cannot be combined with files or --corpus to train on".into());
+    }
+    if flags.bidirectional && (flags.load.is_some() || flags.import_csv.is_some() || flags.merge) {
+        return Err("--bidirectional needs to train on text, not on saved models".into());
+    }
+    // the backward model is trained on stdin again
+    let mut stdin = None;
+    if flags.bidirectional && trains_on_stdin {
+        let mut buffer = Vec::new();
+        decompress(io::stdin().lock())?.read_to_end(&mut buffer)?;
+        stdin = Some(buffer);
+    }
+
+    // a saved model takes precedence over stdin
+    let mut stats = match &flags.load {
+        _ if flags.merge => merge_models(flags.load.iter().chain(&flags.files), &flags)?,
+        Some(path) => load_model(path, &flags)?,
+        None if flags.import_csv.is_some() => {
+            let path = flags.import_csv.as_deref().unwrap_or_default();
+            format::read_csv(open(path)?, &config)
+                .map_err(|e| format!("failed to import {}: {}", path, e))?
         },
+        None => train(&flags, &config, stdin.as_deref())?,
     };
+    let mut backward = None;
+    if flags.bidirectional {
+        let config = TrainConfig { reverse: !config.reverse, ..config.clone() };
+        backward = Some(train(&flags, &config, stdin.as_deref())?);
+    }
+
+    if let Some(min_count) = flags.min_count {
+        let pruned = stats.prune(min_count);
+        if flags.verbose {
+            eprintln!("pruned {} transitions seen less than {} times", pruned, min_count);
+        }
+        if let Some(backward) = &mut backward {
+            backward.prune(min_count);
+        }
+    }
+
+    if stats.is_empty() {
+        return Err("no training data".into());
+    }
+
+    if flags.verbose {
+        describe(&stats);
+    }
+
+    if let Some(path) = &flags.save {
+        let file = File::create(path)
+            .map_err(|e| format!("failed to create {}: {}", path, e))?;
+        model::save_as(BufWriter::new(file), &stats, flags.model_format)?;
+    }
+
+    let mut writer: Box<dyn Write> = match &flags.output {
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(|e| format!("failed to create {}: {}", path, e))?;
+            Box::new(BufWriter::new(file))
+        },
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    match flags.print {
+        Some(Format::Debug) => {
+            let mut usage = determine_highest_usage(&stats);
+            if let Some(prefix) = &flags.print_prefix {
+                let prefix = normalize(prefix, &stats.normalization);
+                usage.retain(|context, _| context.starts_with(&prefix));
+            }
+            writeln!(writer, "{:#?}", usage)?;
+            return Ok(writer.flush()?);
+        },
+        Some(Format::Dot) => return Ok(format::dot(writer, &stats)?),
+        Some(Format::Json) => return Ok(format::json(writer, &stats)?),
+        Some(Format::Csv) => return Ok(format::csv(writer, &stats)?),
+        None => (),
+    }
+
+    if let Some(word) = &flags.query {
+        query(&mut writer, &stats, word)?;
+        return Ok(writer.flush()?);
+    }
+
+    if let Some(max_len) = flags.cycles {
+        cycles(&mut writer, &stats, max_len)?;
+        return Ok(writer.flush()?);
+    }
+
+    if flags.summary {
+        summary(&mut writer, &stats)?;
+        return Ok(writer.flush()?);
+    }
+
+    if flags.entropy {
+        entropy(&mut writer, &stats)?;
+        return Ok(writer.flush()?);
+    }
+
+    if let Some((word, n)) = &flags.top {
+        top(&mut writer, &stats, word, *n)?;
+        return Ok(writer.flush()?);
+    }
+
+    if let Some(path) = &flags.score {
+        let reader = match path.as_ref() {
+            "-" => decompress(io::stdin().lock())?,
+            _ => open(path)?,
+        };
+        let lines = reader.lines().collect::<io::Result<Vec<_>>>()?;
+        let score = score(&stats, lines, flags.smoothing.unwrap_or(1.0));
+        if score.words == 0 {
+            return Err("nothing to score".into());
+        }
+        writeln!(writer, "log-probability: {:.4}", score.log_prob)?;
+        writeln!(writer, "perplexity: {:.4}", score.perplexity())?;
+        return Ok(writer.flush()?);
+    }
+
+    let separator = match (&flags.delimiter, stats.tokens) {
+        (Some(delimiter), _) => delimiter.as_str(),
+        (None, Tokens::Words) | (None, Tokens::Punct) => " ",
+        (None, Tokens::Chars) => "",
+    };
+
+    let deterministic = match flags.beam {
+        _ if flags.greedy => Some(greedy(&stats, &flags)?),
+        Some(width) => Some(beam(&stats, &flags, width)?),
+        None => None,
+    };
+    if let Some(words) = deterministic {
+        if flags.show_meta {
+            eprintln!("words: {}", flags.words);
+            if let Some(word) = words.first() {
+                eprintln!("start: {}", word);
+            }
+        }
+        // the sentence comes out the same every time
+        paragraphs(&mut writer, &flags, |writer, _| {
+            let mut column = 0;
+            for _ in 0..flags.paragraph.unwrap_or(1) {
+                let sentence = words.iter().map(|word| Cow::from(stats.surface(word))).collect();
+                write_words(writer, sentence, &stats, &flags, separator, &mut column)?;
+            }
+            Ok(())
+        })?;
+        return Ok(writer.flush()?);
+    }
 
     // make up some random gibberish
-    let sentence = Usage::new(thres, &usage)
-        .take(words);
+    if flags.min_words > flags.words {
+        return Err("--min-words must not be greater than --max-words".into());
+    }
+    // a random seed has to be known to be shown
+    let seed = match flags.show_meta {
+        true => Some(flags.seed.unwrap_or_else(rand::random)),
+        false => flags.seed,
+    };
+    if flags.show_meta {
+        eprintln!("seed: {}", seed.unwrap_or_default());
+        eprintln!("threshold: {}", flags.thres);
+        eprintln!("words: {}", flags.words);
+    }
+    let config = GeneratorConfig {
+        threshold: flags.thres,
+        temperature: flags.temperature,
+        smoothing: flags.smoothing.unwrap_or(0.0),
+        skip_unknown: flags.skip_unknown,
+        seed,
+        min_words: flags.min_words,
+        max_words: Some(flags.words),
+        no_repeat: flags.no_repeat,
+        start: flags.start.clone(),
+        restart_on_dead_end: flags.restart_on_dead_end,
+        stop_prob: flags.stop_prob,
+        backoff: flags.backoff,
+        max_attempts: flags.max_attempts,
+        sampling: flags.sampling,
+    };
+    let sampler = Sampler::with_config(&stats, &config);
+    let mut usage = Usage::with_config(&sampler, &config)?;
+    if let Some(backward) = &backward {
+        usage.set_end(backward, flags.end.as_deref())?;
+    }
+
+    if flags.interactive {
+        // every line read is the start word of a new sentence
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            let start = Some(line.trim()).filter(|word| !word.is_empty());
+            if let Err(e) = usage.set_start(start) {
+                eprintln!("error: {}", e);
+                continue;
+            }
+            paragraph(&mut writer, &mut usage, &stats, &flags, separator)?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+        return Ok(());
+    }
 
-    write_sentence(sentence)
-        .expect("failed to write sentence")
+    paragraphs(&mut writer, &flags, |writer, restart| {
+        if restart {
+            usage.restart();
+        }
+        paragraph(writer, &mut usage, &stats, &flags, separator)
+    })?;
+    Ok(writer.flush()?)
 }
 
-fn usage(prog_name: &str) {
-    println!("usage: {} [-p <print-words-graph>] [-t <threshold>] [-w <words>]", prog_name);
+// writes -n paragraphs with `paragraph`, each on lines of their own, and
+// telling it whether one was written before
+fn paragraphs<W, F>(writer: &mut W, flags: &Flags, mut paragraph: F) -> io::Result<()>
+where
+    W: Write,
+    F: FnMut(&mut W, bool) -> io::Result<()>,
+{
+    for i in 0..flags.count {
+        if i > 0 {
+            // paragraphs are set apart by a blank line
+            if flags.paragraph.is_some() {
+                writer.write_all(b"\n")?;
+            }
+            writer.write_all(b"\n")?;
+        }
+        paragraph(writer, i > 0)?;
+    }
+    if !flags.no_newline {
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
 }
 
-fn parse_arguments(it: impl Iterator<Item = String>) -> Result<Arguments, Box<dyn std::error::Error>> {
-    let mut kind = ArgumentKind::Flag;
-    let mut args = None;
-    for arg in it {
-        match kind {
-            ArgumentKind::Flag => match arg.as_ref() {
-                "-p" => return Ok(Arguments::Print),
-                "-t" => kind = ArgumentKind::Threshold,
-                "-w" => kind = ArgumentKind::Words,
-                _ => return Err(format!("invalid flag: {}", arg).into())
-            },
-            ArgumentKind::Threshold => {
-                match args {
-                    None => args = Some(Flags { thres: arg.parse()?, words: 100 }),
-                    Some(ref mut f) => f.thres = arg.parse()?,
-                };
-                kind = ArgumentKind::Flag;
-            },
-            ArgumentKind::Words => {
-                match args {
-                    None => args = Some(Flags { thres: 0.75, words: arg.parse()? }),
-                    Some(ref mut f) => f.words = arg.parse()?,
-                };
-                kind = ArgumentKind::Flag;
+// trains on every file and corpus given, or on stdin without any
+fn train(flags: &Flags, config: &TrainConfig, stdin: Option<&[u8]>) -> Result<Stats, Box<dyn std::error::Error>> {
+    let progress = match flags.progress {
+        true if stdin.is_some() => Some(Progress::new(stdin.map(|stdin| stdin.len() as u64))),
+        true if flags.files.is_empty() && flags.corpora.is_empty() => Some(Progress::new(None)),
+        true => {
+            let paths = flags.files.iter().chain(flags.corpora.iter().map(|(path, _)| path));
+            Some(Progress::new(paths.map(|path| uncompressed_len(path)).sum()))
+        },
+        false => None,
+    };
+
+    if flags.files.is_empty() && flags.corpora.is_empty() {
+        let stats = match stdin {
+            Some(stdin) => read_stats(track(stdin, &progress), config)?,
+            None => read_stats(track(decompress(io::stdin().lock())?, &progress), config)?,
+        };
+        if let Some(progress) = &progress {
+            progress.report();
+        }
+        return Ok(stats);
+    }
+
+    // a newline between files keeps their lines apart
+    let mut reader: Box<dyn Read> = Box::new(io::empty());
+    for path in &flags.files {
+        reader = Box::new(reader.chain(track(open(path)?, &progress)).chain(&b"\n"[..]));
+    }
+    let mut stats = read_stats(BufReader::new(reader), config)?;
+    for (path, weight) in &flags.corpora {
+        let config = TrainConfig { weight: *weight, ..config.clone() };
+        stats.merge(read_stats(track(open(path)?, &progress), &config)?);
+    }
+    if let Some(progress) = &progress {
+        progress.report();
+    }
+    Ok(stats)
+}
+
+// how many lines are read between progress reports
+const PROGRESS_EVERY: u64 = 100_000;
+
+// how much of the input was read so far, out of `total` bytes if known
+struct Progress {
+    bytes: Cell<u64>,
+    lines: Cell<u64>,
+    total: Option<u64>,
+}
+
+// counts what is read from `inner` towards `progress`
+struct Tracked<'a, R> {
+    inner: R,
+    progress: &'a Progress,
+}
+
+impl Progress {
+    fn new(total: Option<u64>) -> Self {
+        Progress {
+            bytes: Cell::new(0),
+            lines: Cell::new(0),
+            total,
+        }
+    }
+
+    fn report(&self) {
+        let (bytes, lines) = (self.bytes.get(), self.lines.get());
+        match self.total {
+            Some(total) if total > 0 => {
+                let percent = (bytes as f64 / total as f64 * 100.0).min(100.0);
+                eprintln!("read {} lines, {} bytes ({:.1}%)", lines, bytes, percent);
             },
+            _ => eprintln!("read {} lines, {} bytes", lines, bytes),
         }
     }
-    Ok(match args {
-        None => Arguments::None,
-        Some(flags) => Arguments::Values(flags),
-    })
 }
 
-fn write_sentence<I>(sentence: I) -> io::Result<()>
-where
-    I: Iterator,
-    <I as Iterator>::Item: AsRef<[u8]>,
-{
-    let stdout = io::stdout();
-    let stdout_lock = stdout.lock();
-    let mut writer = BufWriter::new(stdout_lock);
+impl<R: Read> Read for Tracked<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        let before = self.progress.lines.get();
+        let lines = before + buf[..n].iter().filter(|&&b| b == b'\n').count() as u64;
+        self.progress.bytes.set(self.progress.bytes.get() + n as u64);
+        self.progress.lines.set(lines);
+        if lines / PROGRESS_EVERY > before / PROGRESS_EVERY {
+            self.progress.report();
+        }
+        Ok(n)
+    }
+}
+
+// buffers `reader`, counting what is read from it when asked to
+fn track<'a, R: Read + 'a>(reader: R, progress: &'a Option<Progress>) -> Box<dyn BufRead + 'a> {
+    match progress {
+        Some(progress) => Box::new(BufReader::new(Tracked { inner: reader, progress })),
+        None => Box::new(BufReader::new(reader)),
+    }
+}
+
+// the size of a file as it is read, unknown when it is gzipped
+fn uncompressed_len(path: &str) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let mut magic = [0; 2];
+    if file.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b] {
+        return None;
+    }
+    file.metadata().ok().map(|metadata| metadata.len())
+}
+
+// writes the next --paragraph sentences of `usage`, each from a fresh start
+fn paragraph<W: Write>(writer: &mut W, usage: &mut Usage, stats: &Stats, flags: &Flags, separator: &str) -> io::Result<()> {
+    // the sentences share lines, so they wrap as one
+    let mut column = 0;
+    for i in 0..flags.paragraph.unwrap_or(1) {
+        if i > 0 {
+            usage.restart();
+        }
+        generate(writer, usage, stats, flags, separator, &mut column)?;
+    }
+    Ok(())
+}
 
-    for word in sentence {
-        writer.write(word.as_ref())?;
-        writer.write(b" ")?;
+// writes the next sentence of `usage`, from `column` on
+fn generate<W: Write>(writer: &mut W, usage: &mut Usage, stats: &Stats, flags: &Flags, separator: &str, column: &mut usize) -> io::Result<()> {
+    let mut sentence = usage.peekable();
+    if flags.verbose {
+        match sentence.peek() {
+            Some(word) => eprintln!("starting from {}", word),
+            None => eprintln!("empty sentence"),
+        }
+    }
+    if let Some(word) = sentence.peek().filter(|_| flags.show_meta) {
+        eprintln!("start: {}", word);
     }
+    let words: Vec<Cow<str>> = sentence.map(|word| Cow::from(stats.surface(word))).collect();
+    if let Some(context) = usage.dead_end().filter(|_| flags.verbose) {
+        eprintln!("dead end at {}", context);
+    }
+    if usage.exhausted() && flags.verbose {
+        eprintln!("gave up after {} attempts", flags.max_attempts.unwrap_or_default());
+    }
+    write_words(writer, words, stats, flags, separator, column)
+}
 
-    writer.write(b"\n")?;
-    writer.flush()
+// writes the words of a sentence in the order they are read, from
+// `column` on
+fn write_words<W: Write>(writer: &mut W, mut words: Vec<Cow<str>>, stats: &Stats, flags: &Flags, separator: &str, column: &mut usize) -> io::Result<()> {
+    if stats.reverse {
+        words.reverse();
+    }
+    if flags.prose {
+        prose(&mut words, stats.tokens == Tokens::Punct);
+    }
+    write_sentence(writer, words.iter(), separator, flags.wrap, column)
 }
 
-fn determine_highest_usage(stats: &Stats) -> HashMap<String, Vec<String>> {
-    let mut usage = HashMap::new();
-    for (word, neighbors) in stats.of.iter() {
-        let mut numbers = Vec::new();
-        let mut words = Vec::new();
-        for (neigh, number) in neighbors.next.iter() {
-            numbers.push(*number);
-            words.push(neigh.clone());
+// reports how big the model is to stderr
+fn describe(stats: &Stats) {
+    let mut words: HashMap<&str, i64> = HashMap::new();
+    let mut transitions = 0;
+    let mut neighbors = 0;
+    let mut contexts = 0;
+    for (_, next) in stats.contexts() {
+        contexts += 1;
+        neighbors += next.len();
+        for (neigh, number) in next {
+            transitions += i64::from(number);
+            if neigh != END {
+                *words.entry(neigh).or_insert(0) += i64::from(number);
+            }
         }
-        let perm = permutation::sort(numbers);
-        let ordered_words = perm.apply_slice(words);
-        usage.insert(word.clone(), ordered_words);
     }
-    usage
+    let frequent = words
+        .iter()
+        .max_by(|(a, x), (b, y)| x.cmp(y).then_with(|| b.cmp(a)));
+
+    eprintln!("distinct words: {}", words.len());
+    eprintln!("transitions: {}", transitions);
+    if let Some((word, number)) = frequent {
+        eprintln!("most frequent word: {} ({} times)", word, number);
+    }
+    eprintln!("average fan-out: {:.2}", neighbors as f64 / contexts as f64);
 }
 
-fn read_stats() -> io::Result<Stats> {
-    let stdin = io::stdin();
-    let stdin_lock = stdin.lock();
-    let reader = BufReader::new(stdin_lock);
+// the neighbors of `word`, the most likely first
+fn successors<'a>(stats: &'a Stats, word: &str) -> Result<Vec<(&'a str, i32)>, String> {
+    let context = word
+        .split_whitespace()
+        .map(|word| normalize(word, &stats.normalization))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let next = stats.next(&context)
+        .ok_or_else(|| format!("{} not found in the model", word))?;
 
-    let mut stats = Stats::new();
+    let mut next: Vec<(&str, i32)> = next.collect();
+    next.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+    Ok(next)
+}
 
-    for line in reader.lines() {
-        let line = line?;
+// prints the neighbors of `word` and how likely each is
+fn query<W: Write>(writer: &mut W, stats: &Stats, word: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let next = successors(stats, word)?;
+    let total: i32 = next.iter().map(|&(_, number)| number).sum();
+    for (neigh, number) in next {
+        let percent = 100.0 * number as f64 / total as f64;
+        writeln!(writer, "{}\t{}\t{:.2}%", neigh, number, percent)?;
+    }
+    Ok(())
+}
 
-        let w_fst = line.split_whitespace();
-        let w_snd = line.split_whitespace().cycle().skip(1);
+// prints the `n` most likely neighbors of `word`
+fn top<W: Write>(writer: &mut W, stats: &Stats, word: &str, n: usize) -> Result<(), Box<dyn std::error::Error>> {
+    for (neigh, number) in successors(stats, word)?.into_iter().take(n) {
+        writeln!(writer, "{}\t{}", neigh, number)?;
+    }
+    Ok(())
+}
 
-        for (fst, snd) in w_fst.zip(w_snd) {
-            let fst: String = fst
-                .chars()
-                .map(normalize)
-                .collect();
-            let snd: String = snd
-                .chars()
-                .map(normalize)
-                .collect();
-            stats.update(fst, snd);
+// prints how big the corpus was, and what was most frequent in it
+fn summary<W: Write>(writer: &mut W, stats: &Stats) -> io::Result<()> {
+    const TOP: usize = 10;
+    let mut words: HashMap<&str, i64> = HashMap::new();
+    let mut transitions = Vec::new();
+    for (context, next) in stats.contexts() {
+        for (neigh, number) in next {
+            transitions.push((context, neigh, number));
+            if neigh != END {
+                *words.entry(neigh).or_insert(0) += i64::from(number);
+            }
         }
     }
+    let mut frequent: Vec<(&str, i64)> = words.iter().map(|(&word, &number)| (word, number)).collect();
+    frequent.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+    transitions.sort_by(|(a, b, x), (c, d, y)| y.cmp(x).then_with(|| (a, b).cmp(&(c, d))));
 
-    Ok(stats)
+    writeln!(writer, "tokens: {}", words.values().sum::<i64>())?;
+    writeln!(writer, "distinct words: {}", words.len())?;
+    writeln!(writer, "distinct transitions: {}", transitions.len())?;
+    writeln!(writer, "top words:")?;
+    for (word, number) in frequent.into_iter().take(TOP) {
+        writeln!(writer, "  {}\t{}", word, number)?;
+    }
+    writeln!(writer, "top transitions:")?;
+    for (context, neigh, number) in transitions.into_iter().take(TOP) {
+        writeln!(writer, "  {} -> {}\t{}", context, neigh, number)?;
+    }
+    Ok(())
+}
+
+// prints every context by how unpredictable its neighbors are, the
+// branch points of the chain first
+fn entropy<W: Write>(writer: &mut W, stats: &Stats) -> io::Result<()> {
+    let mut contexts: Vec<(&str, f64, usize)> = stats
+        .contexts()
+        .filter_map(|(context, _)| stats.get(context).map(|stat| (context, stat.entropy(), stat.len())))
+        .collect();
+    contexts.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    for (context, entropy, neighbors) in contexts {
+        writeln!(writer, "{}\t{:.4}\t{}", context, entropy, neighbors)?;
+    }
+    Ok(())
+}
+
+// follows the most frequent neighbor of every context, from the start
+// until the end of the sentence, a dead end or a context seen before
+fn greedy(stats: &Stats, flags: &Flags) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let usage = determine_highest_usage(stats);
+    let (mut context, mut words) = starting_point(stats, flags)?;
+    let mut seen = HashSet::new();
+    while words.len() < flags.words && seen.insert(context.clone()) {
+        let word = match usage.get(&context).and_then(|next| next.first()) {
+            Some((word, _)) if word != END => word,
+            _ => break,
+        };
+        words.push(word.clone());
+        context = stats.advance(&context, word);
+    }
+    Ok(words)
+}
+
+// keeps the `width` likeliest sentences so far at every step, returning
+// the likeliest of all those that end
+fn beam(stats: &Stats, flags: &Flags, width: usize) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let (context, words) = starting_point(stats, flags)?;
+    let mut beams = vec![(0.0, context, words)];
+    let mut best: Option<(f64, Vec<String>)> = None;
+    while !beams.is_empty() {
+        let mut next = Vec::new();
+        for (log_prob, context, words) in beams {
+            let stat = match stats.get(&context) {
+                Some(stat) if words.len() < flags.words => stat,
+                // cut short, or at a dead end
+                _ => {
+                    if words.len() >= flags.min_words {
+                        finish(&mut best, log_prob, words);
+                    }
+                    continue;
+                },
+            };
+            let total = f64::from(stat.total());
+            for (neigh, number) in stats.next(&context).into_iter().flatten() {
+                let log_prob = log_prob + (f64::from(number) / total).ln();
+                if neigh == END {
+                    if words.len() >= flags.min_words {
+                        finish(&mut best, log_prob, words.clone());
+                    }
+                    continue;
+                }
+                let mut words = words.clone();
+                words.push(neigh.to_string());
+                next.push((log_prob, stats.advance(&context, neigh), words));
+            }
+        }
+        next.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.2.cmp(&b.2)));
+        next.truncate(width);
+        // sentences only get less likely as they go on
+        if let Some((best, _)) = &best {
+            next.retain(|&(log_prob, _, _)| log_prob > *best);
+        }
+        beams = next;
+    }
+
+    let (_, words) = best.ok_or("no sentence has at least --min-words words")?;
+    Ok(words)
+}
+
+fn finish(best: &mut Option<(f64, Vec<String>)>, log_prob: f64, words: Vec<String>) {
+    if best.as_ref().is_none_or(|&(best, _)| log_prob > best) {
+        *best = Some((log_prob, words));
+    }
+}
+
+// the context deterministic sentences start from, and their words so far
+fn starting_point(stats: &Stats, flags: &Flags) -> Result<(String, Vec<String>), String> {
+    let start = Some(stats.start()).filter(|start| stats.get(start).is_some());
+    match (&flags.start, start) {
+        (Some(word), start) => {
+            let normalized = normalize(word, &stats.normalization);
+            let context = match start {
+                Some(start) => stats.advance(&start, &normalized),
+                None => normalized.clone(),
+            };
+            match stats.get(&context) {
+                Some(_) => Ok((context, vec![normalized])),
+                None => Err(format!("{} not found in the model", word)),
+            }
+        },
+        (None, Some(start)) => Ok((start, Vec::new())),
+        // without start tokens, from the most frequent context
+        (None, None) => {
+            let context = stats
+                .contexts()
+                .filter_map(|(context, _)| stats.get(context).map(|stat| (stat.total(), context)))
+                .max_by(|(x, a), (y, b)| x.cmp(y).then_with(|| b.cmp(a)))
+                .map(|(_, context)| context.to_string())
+                .ok_or("nothing to generate from")?;
+            let words = context.split(' ').map(str::to_string).collect();
+            Ok((context, words))
+        },
+    }
+}
+
+// the loops of the chain found so far, walking it from `start`
+struct Cycles<'a> {
+    stats: &'a Stats,
+    start: &'a str,
+    max_len: usize,
+    words: Vec<&'a str>,
+    contexts: Vec<String>,
+    found: Vec<(Vec<&'a str>, f64)>,
 }
 
-fn normalize(ch: char) -> char {
-    let mut buf = [0_u8; 4];
-    let encoded = ch.encode_utf8(&mut buf[..]);
-    encoded
-        .nfkd()
-        .flat_map(|ch| ch.to_lowercase())
-        .nth(0)
-        .unwrap()
+impl<'a> Cycles<'a> {
+    // every loop is only found from the first of its contexts, sorted
+    // by their words, so none of them is reported twice
+    fn walk(&mut self, context: &str, chance: f64) {
+        let total = match self.stats.get(context) {
+            Some(stat) => f64::from(stat.total()),
+            None => return,
+        };
+        for (neigh, number) in self.stats.next(context).into_iter().flatten() {
+            let next = self.stats.advance(context, neigh);
+            if neigh == END || next.as_str() < self.start || self.contexts.contains(&next) {
+                continue;
+            }
+            let chance = chance * f64::from(number) / total;
+            self.words.push(neigh);
+            if next == self.start {
+                // starting from the last word, which leads into the loop
+                let mut words = self.words.clone();
+                words.rotate_right(1);
+                self.found.push((words, chance));
+            } else if self.words.len() < self.max_len {
+                self.contexts.push(next.clone());
+                self.walk(&next, chance);
+                self.contexts.pop();
+            }
+            self.words.pop();
+        }
+    }
 }
 
-impl Stat {
-    fn new() -> Self {
-        Stat { next: HashMap::new() }
+// prints every loop of at most `max_len` words the chain may get stuck
+// in, along with how likely it is to go all the way around once
+fn cycles<W: Write>(writer: &mut W, stats: &Stats, max_len: usize) -> io::Result<()> {
+    let mut found = Vec::new();
+    for (start, _) in stats.contexts() {
+        let mut cycles = Cycles {
+            stats,
+            start,
+            max_len,
+            words: Vec::new(),
+            contexts: Vec::new(),
+            found,
+        };
+        cycles.walk(start, 1.0);
+        found = cycles.found;
+    }
+    found.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    for (words, chance) in found {
+        writeln!(writer, "{}\t{:.6}", words.join(" "), chance)?;
     }
+    Ok(())
+}
+
+// opens `path`, decompressing it on the fly when it is gzipped
+fn open(path: &str) -> Result<Box<dyn BufRead>, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("failed to open {}: {}", path, e))?;
+    decompress(BufReader::new(file))
+        .map_err(|e| format!("failed to read {}: {}", path, e))
 }
 
-impl Stats {
-    fn new() -> Self {
-        Stats { of: HashMap::new() }
+fn decompress<R: BufRead + 'static>(mut reader: R) -> io::Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn BufRead> = match reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        true => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        false => Box::new(reader),
+    };
+    // files saved by some editors start with a byte order mark, and files
+    // are read one after another
+    let bom = '\u{feff}'.len_utf8();
+    if reader.fill_buf()?.starts_with("\u{feff}".as_bytes()) {
+        reader.consume(bom);
     }
+    Ok(reader)
+}
 
-    fn update(&mut self, word: String, neigh: String) {
-        let stat = self.of.entry(word).or_insert_with(Stat::new);
-        let number = stat.next.entry(neigh).or_insert(0);
-        *number += 1;
+fn load_model(path: &str, flags: &Flags) -> Result<Stats, String> {
+    let reader = open(path)?;
+    let stats = if flags.no_verify {
+        model::load_unverified(reader)
+    } else {
+        model::load(reader)
+    };
+    let stats = stats
+        .map_err(|e| format!("failed to load {}: {}", path, e))?;
+
+    // settings given explicitly must match the loaded model
+    mismatch("order", stats.order, flags.order)?;
+    mismatch("tokens", stats.tokens, flags.tokens)?;
+    mismatch("reversing", stats.reverse, flags.reverse)?;
+    mismatch(
+        "token regex",
+        stats.token_regex.as_ref().map_or("none", Regex::as_str),
+        flags.token_regex.as_ref().map(Regex::as_str),
+    )?;
+    mismatch("normalization", stats.normalization.form, flags.form)?;
+    mismatch("lowercasing", stats.normalization.lowercase, flags.lowercase)?;
+    Ok(stats)
+}
+
+// loads every `<path>[:<weight>]` and sums them up into a single model
+fn merge_models<'a>(models: impl Iterator<Item = &'a String>, flags: &Flags) -> Result<Stats, String> {
+    let mut merged: Option<Stats> = None;
+    for model in models {
+        let (path, weight) = match model.rfind(':') {
+            Some(i) => match model[i + 1..].parse::<f64>() {
+                // counts are rounded, so lighter weights would weigh 1
+                Ok(weight) if weight.is_finite() && weight.round() >= 1.0 => (&model[..i], weight),
+                Ok(_) => return Err(format!("weight of {} must be a number that rounds to at least 1", &model[..i])),
+                Err(_) => (model.as_str(), 1.0),
+            },
+            None => (model.as_str(), 1.0),
+        };
+        let mut stats = load_model(path, flags)?;
+        if weight != 1.0 {
+            stats.scale(weight);
+        }
+        match &mut merged {
+            None => merged = Some(stats),
+            Some(merged) => {
+                let same = merged.order == stats.order
+                    && merged.tokens == stats.tokens
+                    && merged.reverse == stats.reverse
+                    && merged.token_regex.as_ref().map(Regex::as_str)
+                        == stats.token_regex.as_ref().map(Regex::as_str)
+                    && merged.normalization == stats.normalization;
+                if !same {
+                    return Err(format!("cannot merge {}, it was trained with different settings", path));
+                }
+                merged.merge(stats);
+            },
+        }
     }
+    merged.ok_or_else(|| "no models to merge".to_string())
 }
 
-impl<'a> Usage<'a> {
-    fn new(threshold: f32, usage: &'a HashMap<String, Vec<String>>) -> Self {
-        let threshold = if threshold < 0.0 || threshold > 1.0 {
-            0.75
-        } else {
-            threshold
+fn mismatch<T: PartialEq + Debug>(name: &str, model: T, requested: Option<T>) -> Result<(), String> {
+    match requested {
+        Some(requested) if requested != model => {
+            Err(format!("model has {} {:?}, but {:?} was requested", name, model, requested))
+        },
+        _ => Ok(()),
+    }
+}
+
+fn usage(prog_name: &str) -> String {
+    format!("usage: {} [-h] [-V] [-p] [--print-prefix <prefix>] [-s <seed>] [--show-meta] [-t <threshold>] [--start <word>] [--bidirectional] [--end <word>] [--temperature <t>] [--sampling <mode>] [-w <words>] [--min-words <n>] [--restart-on-deadend] [--stop-prob <p>] [--max-attempts <n>] [--backoff] [--greedy] [--beam <k>] [--no-repeat <n>] [-n <count>] [--interactive] [--delimiter <str>] [--no-newline] [--wrap <cols>] [--paragraph <n>] [--prose] [--order <n>] [--char] [--tokenizer <words|punct>] [--split-on <chars>] [--split-only-on <chars>] [--token-regex <regex>] [--reverse] [--normalization <form>] [--no-lowercase] [--min-count <n>] [--number-token <mode>] [--stopwords <path>] [--min-token-len <n>] [--lossy] [--strip-html] [--segment <line|sentence>] [--no-self-loops] [--vocab <k>] [--threads <n>] [--skip-unknown] [-v] [--progress] [-o <path>] [--save <path>] [--load <path>] [--import-csv <path>] [--format-model <json|bincode>] [--no-verify] [--merge] [--corpus <path>[:<weight>]] [--format <debug|dot|json|csv>] [--query <word>] [--top <word>:<n>] [--cycles <len>] [--entropy] [--summary] [--score <path>] [--smoothing <k>] [<file>...]", prog_name)
+}
+
+// how wide the flags are in the help, before their descriptions
+const HELP_COLUMN: usize = 28;
+
+fn help(prog_name: &str) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    writeln!(out, "{}", usage(prog_name))?;
+    writeln!(out)?;
+    writeln!(out, "Reads a corpus from the given files, or stdin, and makes up some random gibberish.")?;
+    writeln!(out)?;
+    writeln!(out, "Short flags can be grouped, as in -vp, and the last one of a group can take its")?;
+    writeln!(out, "value right after it, as in -t0.8 or -vt0.8, or after an equals sign, as in -t=0.8.")?;
+    writeln!(out)?;
+    writeln!(out, "options:")?;
+    for (flag, description) in OPTIONS {
+        // flags too wide for the column get a line of their own
+        if flag.len() > HELP_COLUMN {
+            writeln!(out, "  {}", flag)?;
+            writeln!(out, "  {:<width$} {}", "", description, width = HELP_COLUMN)?;
+            continue;
+        }
+        writeln!(out, "  {:<width$} {}", flag, description, width = HELP_COLUMN)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "environment, overridden by the options above:")?;
+    for (var, flag) in ENVIRONMENT {
+        writeln!(out, "  {:<width$} same as {}", var, flag, width = HELP_COLUMN)?;
+    }
+    out.flush()
+}
+
+fn version() -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    writeln!(out, "{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))?;
+    out.flush()
+}
+
+// the flags set in the environment, checked before they are mixed with
+// the command line so errors name the variable
+fn environment() -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    for &(var, flag) in ENVIRONMENT {
+        let value = match std::env::var(var) {
+            Ok(value) => value,
+            Err(_) => continue,
         };
-        let mut keys = usage.keys();
-        let mut rounds = (threshold * 10.0) as i32;
-        let mut first = String::from("A");
-        loop {
-            match keys.next() {
-                Some(_) if rounds == 0 => break,
-                Some(k) => {
-                    first.clear();
-                    first.push_str(k);
+        match flag {
+            "-t" => parse_value::<f32>(var, &value).map(drop)?,
+            "-w" => parse_value::<usize>(var, &value).map(drop)?,
+            _ => parse_value::<u64>(var, &value).map(drop)?,
+        }
+        // values that parse can still be out of range for their flag
+        parse_arguments(vec![flag.to_string(), value.clone()].into_iter())
+            .map_err(|e| format!("invalid value for {} '{}': {}", var, value, e))?;
+        args.push(flag.to_string());
+        args.push(value);
+    }
+    Ok(args)
+}
+
+fn parse_arguments(mut it: impl Iterator<Item = String>) -> Result<Arguments, Box<dyn std::error::Error>> {
+    let mut kind = ArgumentKind::Flag;
+    let mut args: Option<Flags> = None;
+    // the flag whose value is expected next
+    let mut flag = String::new();
+    // what is left of the last argument once taken apart
+    let mut pieces = VecDeque::new();
+    loop {
+        let arg = match pieces.pop_front() {
+            Some(piece) => piece,
+            // values are taken as they are, even when they look like flags
+            None => match it.next() {
+                Some(arg) if matches!(kind, ArgumentKind::Flag) => {
+                    pieces.extend(split_value(arg).into_iter().flat_map(split_short));
+                    pieces.pop_front().unwrap()
                 },
+                Some(arg) => arg,
                 None => break,
+            },
+        };
+        if let ArgumentKind::Flag = kind {
+            flag.clone_from(&arg);
+        }
+        match kind {
+            ArgumentKind::Flag => match arg.as_ref() {
+                "-h" | "--help" => return Ok(Arguments::Help),
+                "-V" | "--version" => return Ok(Arguments::Version),
+                "-p" | "--print" => args.get_or_insert_with(Flags::default).print = Some(Format::Debug),
+                "--print-prefix" => kind = ArgumentKind::PrintPrefix,
+                "--entropy" => args.get_or_insert_with(Flags::default).entropy = true,
+                "--summary" => args.get_or_insert_with(Flags::default).summary = true,
+                "-s" | "--seed" => kind = ArgumentKind::Seed,
+                "-t" | "--threshold" => kind = ArgumentKind::Threshold,
+                "--start" => kind = ArgumentKind::Start,
+                "--bidirectional" => args.get_or_insert_with(Flags::default).bidirectional = true,
+                "--end" => kind = ArgumentKind::End,
+                "--delimiter" => kind = ArgumentKind::Delimiter,
+                "--no-newline" => args.get_or_insert_with(Flags::default).no_newline = true,
+                "--wrap" => kind = ArgumentKind::Wrap,
+                "--paragraph" => kind = ArgumentKind::Paragraph,
+                "--restart-on-deadend" => args.get_or_insert_with(Flags::default).restart_on_dead_end = true,
+                "--backoff" => args.get_or_insert_with(Flags::default).backoff = true,
+                "--greedy" => args.get_or_insert_with(Flags::default).greedy = true,
+                "--beam" => kind = ArgumentKind::Beam,
+                "--sampling" => kind = ArgumentKind::Sampling,
+                "--max-attempts" => kind = ArgumentKind::MaxAttempts,
+                "--interactive" => args.get_or_insert_with(Flags::default).interactive = true,
+                "--prose" => args.get_or_insert_with(Flags::default).prose = true,
+                "--temperature" => kind = ArgumentKind::Temperature,
+                "--stop-prob" => kind = ArgumentKind::StopProb,
+                "-w" | "--words" | "--max-words" => kind = ArgumentKind::Words,
+                "--min-words" => kind = ArgumentKind::MinWords,
+                "--no-repeat" => kind = ArgumentKind::NoRepeat,
+                "-n" | "--count" => kind = ArgumentKind::Count,
+                "--order" => kind = ArgumentKind::Order,
+                "--normalization" => kind = ArgumentKind::Normalization,
+                "--no-lowercase" | "--preserve-case" => args.get_or_insert_with(Flags::default).lowercase = Some(false),
+                "--min-count" => kind = ArgumentKind::MinCount,
+                "--vocab" => kind = ArgumentKind::Vocab,
+                "--threads" => kind = ArgumentKind::Threads,
+                "--number-token" => kind = ArgumentKind::NumberToken,
+                "--segment" => kind = ArgumentKind::Segment,
+                "--stopwords" => kind = ArgumentKind::Stopwords,
+                "--min-token-len" => kind = ArgumentKind::MinTokenLen,
+                "--lossy" => args.get_or_insert_with(Flags::default).lossy = true,
+                "--strip-html" => args.get_or_insert_with(Flags::default).strip_html = true,
+                "--no-self-loops" => args.get_or_insert_with(Flags::default).no_self_loops = true,
+                "--skip-unknown" => args.get_or_insert_with(Flags::default).skip_unknown = true,
+                "--show-meta" => args.get_or_insert_with(Flags::default).show_meta = true,
+                "-v" | "--verbose" => args.get_or_insert_with(Flags::default).verbose = true,
+                "--progress" => args.get_or_insert_with(Flags::default).progress = true,
+                "--reverse" => args.get_or_insert_with(Flags::default).reverse = Some(true),
+                "--tokenizer" => kind = ArgumentKind::Tokenizer,
+                "--token-regex" => kind = ArgumentKind::TokenRegex,
+                "--split-on" => kind = ArgumentKind::SplitOn,
+                "--split-only-on" => kind = ArgumentKind::SplitOnlyOn,
+                "--char" => args.get_or_insert_with(Flags::default).tokens = Some(Tokens::Chars),
+                "-o" | "--output" => kind = ArgumentKind::Output,
+                "--save" => kind = ArgumentKind::Save,
+                "--load" => kind = ArgumentKind::Load,
+                "--import-csv" => kind = ArgumentKind::ImportCsv,
+                "--format-model" => kind = ArgumentKind::ModelFormat,
+                "--no-verify" => args.get_or_insert_with(Flags::default).no_verify = true,
+                "--merge" => args.get_or_insert_with(Flags::default).merge = true,
+                "--corpus" => kind = ArgumentKind::Corpus,
+                "--format" => kind = ArgumentKind::Format,
+                "--query" => kind = ArgumentKind::Query,
+                "--top" => kind = ArgumentKind::Top,
+                "--cycles" => kind = ArgumentKind::Cycles,
+                "--score" => kind = ArgumentKind::Score,
+                "--smoothing" => kind = ArgumentKind::Smoothing,
+                _ if !arg.starts_with('-') => args.get_or_insert_with(Flags::default).files.push(arg),
+                _ => return Err(format!("invalid flag: {}", arg).into())
+            },
+            ArgumentKind::Threshold => {
+                let threshold: f32 = parse_value(&flag, &arg)?;
+                if !(0.0..=1.0).contains(&threshold) {
+                    return Err("threshold must be between 0.0 and 1.0".into());
+                }
+                args.get_or_insert_with(Flags::default).thres = threshold;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::StopProb => {
+                let stop_prob: f64 = parse_value(&flag, &arg)?;
+                if !(0.0..=1.0).contains(&stop_prob) {
+                    return Err("stop probability must be between 0 and 1".into());
+                }
+                args.get_or_insert_with(Flags::default).stop_prob = stop_prob;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Temperature => {
+                let temperature: f64 = parse_value(&flag, &arg)?;
+                if temperature <= 0.0 {
+                    return Err("temperature must be greater than 0".into());
+                }
+                args.get_or_insert_with(Flags::default).temperature = temperature;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Words => {
+                let words = parse_value(&flag, &arg)?;
+                if words == 0 {
+                    return Err(format!("{} must be at least 1", flag).into());
+                }
+                args.get_or_insert_with(Flags::default).words = words;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::MinWords => {
+                args.get_or_insert_with(Flags::default).min_words = parse_value(&flag, &arg)?;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::NoRepeat => {
+                args.get_or_insert_with(Flags::default).no_repeat = parse_value(&flag, &arg)?;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Count => {
+                args.get_or_insert_with(Flags::default).count = parse_value(&flag, &arg)?;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Seed => {
+                args.get_or_insert_with(Flags::default).seed = Some(parse_value(&flag, &arg)?);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Order => {
+                let order = parse_value(&flag, &arg)?;
+                if order == 0 {
+                    return Err("order must be at least 1".into());
+                }
+                args.get_or_insert_with(Flags::default).order = Some(order);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Normalization => {
+                args.get_or_insert_with(Flags::default).form = Some(arg.parse()?);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::MinCount => {
+                args.get_or_insert_with(Flags::default).min_count = Some(parse_value(&flag, &arg)?);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Vocab => {
+                args.get_or_insert_with(Flags::default).vocab = Some(parse_value(&flag, &arg)?);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Threads => {
+                args.get_or_insert_with(Flags::default).threads = parse_value(&flag, &arg)?;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Output => {
+                args.get_or_insert_with(Flags::default).output = Some(arg);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Save => {
+                args.get_or_insert_with(Flags::default).save = Some(arg);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Corpus => {
+                let corpus = match arg.rsplit_once(':') {
+                    Some((path, weight)) => match weight.parse::<i32>() {
+                        Ok(weight) if weight > 0 => (path.to_string(), weight),
+                        Ok(_) => return Err(format!("weight of {} must be greater than 0", path).into()),
+                        Err(_) if weight.parse::<f64>().is_ok() => {
+                            return Err(format!("weight of {} must be a whole number", path).into());
+                        },
+                        Err(_) => (arg, 1),
+                    },
+                    None => (arg, 1),
+                };
+                args.get_or_insert_with(Flags::default).corpora.push(corpus);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::ImportCsv => {
+                args.get_or_insert_with(Flags::default).import_csv = Some(arg);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Load => {
+                args.get_or_insert_with(Flags::default).load = Some(arg);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::ModelFormat => {
+                args.get_or_insert_with(Flags::default).model_format = arg.parse()?;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Format => {
+                let format = match arg.as_ref() {
+                    "debug" => Format::Debug,
+                    "dot" => Format::Dot,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    _ => return Err(format!("invalid format: {}", arg).into()),
+                };
+                args.get_or_insert_with(Flags::default).print = Some(format);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::PrintPrefix => {
+                let flags = args.get_or_insert_with(Flags::default);
+                flags.print = Some(Format::Debug);
+                flags.print_prefix = Some(arg);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Query => {
+                args.get_or_insert_with(Flags::default).query = Some(arg);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Top => {
+                let (word, n) = arg
+                    .rsplit_once(':')
+                    .ok_or_else(|| format!("invalid top: {}, expected <word>:<n>", arg))?;
+                args.get_or_insert_with(Flags::default).top = Some((word.to_string(), parse_value(&flag, n)?));
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::MaxAttempts => {
+                args.get_or_insert_with(Flags::default).max_attempts = Some(parse_value(&flag, &arg)?);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Sampling => {
+                args.get_or_insert_with(Flags::default).sampling = arg.parse()?;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Beam => {
+                let width: usize = parse_value(&flag, &arg)?;
+                if width == 0 {
+                    return Err("beam width must be greater than 0".into());
+                }
+                args.get_or_insert_with(Flags::default).beam = Some(width);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Cycles => {
+                args.get_or_insert_with(Flags::default).cycles = Some(parse_value(&flag, &arg)?);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Score => {
+                args.get_or_insert_with(Flags::default).score = Some(arg);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Delimiter => {
+                args.get_or_insert_with(Flags::default).delimiter = Some(unescape(&arg));
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Tokenizer => {
+                let tokens = match arg.as_ref() {
+                    "words" => Tokens::Words,
+                    "punct" => Tokens::Punct,
+                    _ => return Err(format!("invalid tokenizer: {}", arg).into()),
+                };
+                args.get_or_insert_with(Flags::default).tokens = Some(tokens);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::NumberToken => {
+                let numbers = match arg.as_ref() {
+                    "keep" => Numbers::Keep,
+                    "collapse" => Numbers::Collapse,
+                    "drop" => Numbers::Drop,
+                    _ => return Err(format!("invalid number token mode: {}", arg).into()),
+                };
+                args.get_or_insert_with(Flags::default).numbers = numbers;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Segment => {
+                let segment = match arg.as_ref() {
+                    "line" => Segment::Line,
+                    "sentence" => Segment::Sentence,
+                    _ => return Err(format!("invalid segment mode: {}", arg).into()),
+                };
+                args.get_or_insert_with(Flags::default).segment = segment;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Stopwords => {
+                args.get_or_insert_with(Flags::default).stopwords = Some(arg);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::MinTokenLen => {
+                args.get_or_insert_with(Flags::default).min_token_len = parse_value(&flag, &arg)?;
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::SplitOn | ArgumentKind::SplitOnlyOn => {
+                let whitespace = matches!(kind, ArgumentKind::SplitOn);
+                let chars = unescape(&arg).chars().collect();
+                args.get_or_insert_with(Flags::default).split_on = Some(Separators { chars, whitespace });
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::TokenRegex => {
+                args.get_or_insert_with(Flags::default).token_regex = Some(parse_value(&flag, &arg)?);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Wrap => {
+                let wrap = parse_value(&flag, &arg)?;
+                if wrap == 0 {
+                    return Err("wrap must be at least 1".into());
+                }
+                args.get_or_insert_with(Flags::default).wrap = Some(wrap);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Paragraph => {
+                let paragraph = parse_value(&flag, &arg)?;
+                if paragraph == 0 {
+                    return Err("paragraph must be at least 1 sentence".into());
+                }
+                args.get_or_insert_with(Flags::default).paragraph = Some(paragraph);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Start => {
+                args.get_or_insert_with(Flags::default).start = Some(arg);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::End => {
+                args.get_or_insert_with(Flags::default).end = Some(arg);
+                kind = ArgumentKind::Flag;
+            },
+            ArgumentKind::Smoothing => {
+                let smoothing: f64 = parse_value(&flag, &arg)?;
+                if !smoothing.is_finite() || smoothing < 0.0 {
+                    return Err(format!("smoothing must be a finite number of at least 0, not {}", arg).into());
+                }
+                args.get_or_insert_with(Flags::default).smoothing = Some(smoothing);
+                kind = ArgumentKind::Flag;
+            },
+        }
+    }
+    if !matches!(kind, ArgumentKind::Flag) {
+        return Err(format!("{} requires a value", flag).into());
+    }
+    Ok(match args {
+        None => Arguments::None,
+        Some(flags) => Arguments::Values(Box::new(flags)),
+    })
+}
+
+fn parse_value<T>(flag: &str, value: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("invalid value for {} '{}': {}", flag, value, e))
+}
+
+// groups the switches of `-vp` apart, and a short flag from the value
+// attached to it like `-t0.8`, which may end a group like `-vt0.8`
+fn split_short(arg: String) -> Vec<String> {
+    let flags = match arg.strip_prefix('-') {
+        Some(flags) if flags.len() > 1 && !flags.starts_with('-') => flags,
+        _ => return vec![arg],
+    };
+    let mut split = Vec::new();
+    for (i, c) in flags.char_indices() {
+        if !SHORT_SWITCHES.contains(c) && !SHORT_OPTIONS.contains(c) {
+            // reported as an invalid flag as a whole
+            return vec![arg];
+        }
+        split.push(format!("-{}", c));
+        if SHORT_OPTIONS.contains(c) {
+            let value = &flags[i + c.len_utf8()..];
+            if !value.is_empty() {
+                split.push(value.to_string());
             }
-            rounds -= 1;
+            break;
         }
-        Usage {
-            usage,
-            threshold,
-            current: first
-                .chars()
-                .map(normalize)
-                .collect(),
+    }
+    split
+}
+
+// splits `--flag=value` and `-f=value` into the flag and its value
+fn split_value(arg: String) -> Vec<String> {
+    if arg.starts_with('-') {
+        if let Some(i) = arg.find('=') {
+            return vec![arg[..i].to_string(), arg[i + 1..].to_string()];
         }
     }
+    vec![arg]
 }
 
-impl Iterator for Usage<'_> {
-    type Item = String;
+// writes the words of a sentence after whatever is up to `column` on the
+// line, a space apart from it, breaking lines before they get wider than
+// `wrap` columns
+fn write_sentence<W, I>(writer: &mut W, sentence: I, separator: &str, wrap: Option<usize>, column: &mut usize) -> io::Result<()>
+where
+    W: Write,
+    I: Iterator,
+    <I as Iterator>::Item: AsRef<str>,
+{
+    for (i, word) in sentence.enumerate() {
+        let word = word.as_ref();
+        let width = word.width();
+        let separator = match i {
+            0 if *column == 0 => "",
+            0 => " ",
+            _ => separator,
+        };
+        if !separator.is_empty() {
+            match wrap {
+                Some(wrap) if *column + separator.width() + width > wrap => {
+                    writer.write_all(b"\n")?;
+                    *column = 0;
+                },
+                _ => {
+                    writer.write_all(separator.as_bytes())?;
+                    *column = match separator.rfind('\n') {
+                        Some(i) => separator[i + 1..].width(),
+                        None => *column + separator.width(),
+                    };
+                },
+            }
+        }
+        writer.write_all(word.as_bytes())?;
+        *column += width;
+    }
+    Ok(())
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut it_word = 0;
-        loop {
-            let mut it_percent = 0;
-            let percent: f32 = loop {
-                let x = rand::random();
-                if x >= self.threshold || it_percent >= 30 {
-                    break x;
+// capitalizes the first word, and ends the sentence with a period,
+// gluing punctuation tokens back onto the words before them if `attach`
+fn prose(words: &mut Vec<Cow<str>>, attach: bool) {
+    if attach {
+        let mut attached: Vec<Cow<str>> = Vec::with_capacity(words.len());
+        let opening = |c: char| "([{\"'¿¡".contains(c);
+        let mut opened = false;
+        // the quote marks that stand for both ends and are still open
+        let mut quoted = String::new();
+        for word in words.drain(..) {
+            let mut closes = word.starts_with(|c: char| !c.is_alphanumeric() && !opening(c));
+            let mut opens = word.chars().all(opening);
+            if let Some(quote) = word.chars().next().filter(|&c| c == '"' || c == '\'') {
+                if word.chars().all(|c| c == quote) {
+                    closes = quoted.contains(quote);
+                    opens = !closes;
+                    if closes {
+                        quoted.retain(|c| c != quote);
+                    } else {
+                        quoted.push(quote);
+                    }
                 }
-                it_percent += 1;
-            };
-            let candidates = self.usage.get(&self.current)?;
-            let char_picked = (percent * (candidates.len() as f32)) as usize;
-            let char_picked = &candidates[char_picked];
-            if char_picked == &self.current || it_word < 30 {
-                it_word += 1;
-                continue;
             }
-            self.current.clear();
-            self.current.push_str(&char_picked);
-            break Some(char_picked.clone());
+            match attached.last_mut() {
+                Some(last) if closes || opened => last.to_mut().push_str(&word),
+                _ => attached.push(word),
+            }
+            opened = opens;
+        }
+        *words = attached;
+    }
+
+    if let Some(first) = words.first_mut() {
+        let mut graphemes = first.graphemes(true);
+        if let Some(grapheme) = graphemes.next() {
+            *first = Cow::Owned(grapheme.to_uppercase() + graphemes.as_str());
+        }
+    }
+    if let Some(last) = words.last_mut() {
+        let ends = last
+            .chars()
+            .next_back()
+            .is_some_and(|c| !c.is_alphanumeric() && !c.is_whitespace());
+        if !ends {
+            last.to_mut().push('.');
+        }
+    }
+}
+
+// turns `\n`, `\t` and `\\` into what they stand for
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('\\') => unescaped.push('\\'),
+            Some(c) => {
+                unescaped.push('\\');
+                unescaped.push(c);
+            },
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Flags {
+            thres: 0.75,
+            temperature: 1.0,
+            words: 100,
+            min_words: 0,
+            no_repeat: 0,
+            count: 1,
+            seed: None,
+            order: None,
+            tokens: None,
+            reverse: None,
+            token_regex: None,
+            form: None,
+            lowercase: None,
+            min_count: None,
+            no_self_loops: false,
+            numbers: Numbers::Keep,
+            stopwords: None,
+            min_token_len: 0,
+            lossy: false,
+            strip_html: false,
+            segment: Segment::Line,
+            split_on: None,
+            vocab: None,
+            threads: 1,
+            skip_unknown: false,
+            verbose: false,
+            print: None,
+            save: None,
+            model_format: ModelFormat::Json,
+            no_verify: false,
+            load: None,
+            import_csv: None,
+            merge: false,
+            query: None,
+            top: None,
+            score: None,
+            smoothing: None,
+            start: None,
+            delimiter: None,
+            no_newline: false,
+            wrap: None,
+            paragraph: None,
+            prose: false,
+            output: None,
+            interactive: false,
+            restart_on_dead_end: false,
+            stop_prob: 0.0,
+            backoff: false,
+            entropy: false,
+            cycles: None,
+            greedy: false,
+            summary: false,
+            print_prefix: None,
+            show_meta: false,
+            beam: None,
+            max_attempts: None,
+            sampling: Sampling::Uniform,
+            progress: false,
+            bidirectional: false,
+            end: None,
+            corpora: Vec::new(),
+            files: Vec::new(),
         }
     }
 }
@@ -0,0 +1,137 @@
+//! Saving and loading trained models.
+//!
+//! Models are stored as JSON by default, so they can be inspected by
+//! hand, or with [`bincode`] when they are too large for that.
+//!
+//! Either way, saved models end with a line holding the CRC32 of
+//! everything before it, in hexadecimal, so that truncated or corrupted
+//! files fail to load instead of being read as a different model.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+use bincode::Options;
+
+use crate::stats::{self, RawStats, RawStatsV1};
+use crate::Stats;
+
+// tells binary models apart from JSON ones, which never start with it
+const MAGIC: &[u8] = b"papagaio";
+
+// a newline, eight hexadecimal digits and a newline
+const CHECKSUM_LEN: usize = 10;
+
+/// How a model is stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelFormat {
+    /// Human readable, see [`Stats`] for its schema.
+    Json,
+    /// Compact and fast to load, but opaque.
+    Bincode,
+}
+
+impl FromStr for ModelFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ModelFormat::Json),
+            "bincode" => Ok(ModelFormat::Bincode),
+            _ => Err(format!("invalid model format: {}", s)),
+        }
+    }
+}
+
+/// Writes a trained model to `writer` as JSON.
+pub fn save<W: Write>(writer: W, stats: &Stats) -> io::Result<()> {
+    save_as(writer, stats, ModelFormat::Json)
+}
+
+/// Writes a trained model to `writer` in the given format.
+pub fn save_as<W: Write>(mut writer: W, stats: &Stats, format: ModelFormat) -> io::Result<()> {
+    let mut bytes = Vec::new();
+    match format {
+        ModelFormat::Json => serde_json::to_writer(&mut bytes, stats)?,
+        ModelFormat::Bincode => {
+            bytes.extend_from_slice(MAGIC);
+            bincode::DefaultOptions::new()
+                .serialize_into(&mut bytes, stats)
+                .map_err(invalid_data)?;
+        },
+    }
+    writer.write_all(&bytes)?;
+    write!(writer, "\n{:08x}\n", crc32fast::hash(&bytes))?;
+    writer.flush()
+}
+
+/// Reads a model previously written with [`save`] or [`save_as`],
+/// detecting its format.
+///
+/// Fails when its checksum is missing or does not match.
+pub fn load<R: Read>(reader: R) -> io::Result<Stats> {
+    read(reader, true)
+}
+
+/// Reads a model without verifying its checksum, which hand-crafted
+/// models may leave out.
+pub fn load_unverified<R: Read>(reader: R) -> io::Result<Stats> {
+    read(reader, false)
+}
+
+fn read<R: Read>(mut reader: R, verify: bool) -> io::Result<Stats> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let (payload, checksum) = split_checksum(&bytes);
+    if verify {
+        match checksum {
+            None => return Err(invalid_data("missing checksum")),
+            Some(checksum) if checksum != crc32fast::hash(payload) => {
+                return Err(invalid_data("checksum mismatch, the model is corrupted"));
+            },
+            Some(_) => (),
+        }
+    }
+
+    match payload.strip_prefix(MAGIC) {
+        Some(payload) => read_bincode(payload),
+        None => Ok(serde_json::from_slice(payload)?),
+    }
+}
+
+// binary models leave no field out, so each version is read in the
+// layout it was written in, which starts with the version itself
+fn read_bincode(payload: &[u8]) -> io::Result<Stats> {
+    let options = bincode::DefaultOptions::new();
+    let version: u32 = options
+        .allow_trailing_bytes()
+        .deserialize(payload)
+        .map_err(invalid_data)?;
+    stats::check_version(version).map_err(invalid_data)?;
+    let raw = match version {
+        1 => options.deserialize::<RawStatsV1>(payload).map(RawStats::from),
+        _ => options.deserialize::<RawStats>(payload),
+    };
+    Stats::try_from(raw.map_err(invalid_data)?).map_err(invalid_data)
+}
+
+// splits the trailing checksum off, if there is one
+fn split_checksum(bytes: &[u8]) -> (&[u8], Option<u32>) {
+    let split = bytes.len().saturating_sub(CHECKSUM_LEN);
+    let (payload, trailer) = bytes.split_at(split);
+    let checksum = match trailer {
+        [b'\n', digits @ .., b'\n'] if digits.len() == 8 => std::str::from_utf8(digits)
+            .ok()
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok()),
+        _ => None,
+    };
+    match checksum {
+        Some(checksum) => (payload, Some(checksum)),
+        None => (bytes, None),
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
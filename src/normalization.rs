@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::{is_nfc_quick, is_nfd_quick, is_nfkc_quick, is_nfkd_quick, IsNormalized, UnicodeNormalization};
+
+/// How words are normalized before being counted.
+///
+/// By default, compatibility characters are folded and the result is
+/// composed again, so accented letters like `ã` are kept whole.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Normalization {
+    pub form: Form,
+    /// Folds every word to lowercase. Without it, proper nouns keep their
+    /// case, at the cost of a larger vocabulary since "Paris" and "paris"
+    /// are then different words.
+    pub lowercase: bool,
+}
+
+/// A Unicode normalization form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Form {
+    Nfc,
+    Nfkc,
+    Nfd,
+    Nfkd,
+    /// Keep words as they are.
+    None,
+}
+
+/// Normalizes a word before it is counted or looked up.
+pub fn normalize(word: &str, normalization: &Normalization) -> String {
+    // ascii is already normalized in every form, and most words are
+    if word.is_ascii() {
+        return match normalization.lowercase {
+            true => word.to_ascii_lowercase(),
+            false => word.to_string(),
+        };
+    }
+
+    let normalized: Cow<str> = match normalization.form {
+        Form::Nfc if is_nfc_quick(word.chars()) != IsNormalized::Yes => word.nfc().collect(),
+        Form::Nfkc if is_nfkc_quick(word.chars()) != IsNormalized::Yes => word.nfkc().collect(),
+        Form::Nfd if is_nfd_quick(word.chars()) != IsNormalized::Yes => word.nfd().collect(),
+        Form::Nfkd if is_nfkd_quick(word.chars()) != IsNormalized::Yes => word.nfkd().collect(),
+        _ => Cow::Borrowed(word),
+    };
+    if normalization.lowercase {
+        normalized.to_lowercase()
+    } else {
+        normalized.into_owned()
+    }
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization { form: Form::Nfkc, lowercase: true }
+    }
+}
+
+impl std::str::FromStr for Form {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nfc" => Ok(Form::Nfc),
+            "nfkc" => Ok(Form::Nfkc),
+            "nfd" => Ok(Form::Nfd),
+            "nfkd" => Ok(Form::Nfkd),
+            "none" => Ok(Form::None),
+            _ => Err(format!("invalid normalization form: {}", s)),
+        }
+    }
+}
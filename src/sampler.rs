@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet};
+
+use rand::Rng;
+
+use crate::{GeneratorConfig, Stats, END, UNK};
+
+/// Draws the next word of any context in constant time.
+///
+/// An alias table is built once for every context of the model, so the
+/// same sampler can be shared by as many sentences as needed.
+///
+/// The `temperature` reshapes the probabilities of each word by raising
+/// its count to the power of `1 / temperature`, and must be greater than
+/// zero. A temperature of 1 keeps them as they are, higher ones flatten
+/// them towards uniform, and as it approaches 0 the most frequent word
+/// is always picked. With `skip_unknown`, [`UNK`] is never drawn.
+///
+/// The [`sampling`](GeneratorConfig::sampling) may restrict each context
+/// to its most frequent words, or favor them further.
+///
+/// With [`smoothing`](GeneratorConfig::smoothing), any word of the
+/// vocabulary may follow any context. With
+/// [`backoff`](GeneratorConfig::backoff), tables are built for the
+/// shorter contexts of higher order models as well.
+#[derive(Clone, Debug)]
+pub struct Sampler<'a> {
+    stats: &'a Stats,
+    tables: HashMap<u32, AliasTable>,
+    pub(crate) end: Option<u32>,
+    smoothing: f64,
+    vocabulary: Vec<u32>,
+    // every word that followed the last words of some context, by them
+    backoff: HashMap<String, AliasTable>,
+}
+
+/// How the next word is drawn among the ones that followed a context.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Sampling {
+    /// In proportion to how often each one followed it, reshaped by the
+    /// temperature.
+    #[default]
+    Uniform,
+    /// In proportion to the exponential of how often each one followed
+    /// it, divided by the temperature, which favors frequent words far
+    /// more.
+    Softmax,
+    /// Only among the `k` that followed it most often.
+    TopK(usize),
+    /// Only among the ones that followed it most often, the fewest that
+    /// make up at least `p` of every time it was followed.
+    TopP(f64),
+}
+
+impl std::str::FromStr for Sampling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid sampling: {}", s);
+        match s.split_once(':') {
+            None if s == "uniform" => Ok(Sampling::Uniform),
+            None if s == "softmax" => Ok(Sampling::Softmax),
+            Some(("topk", k)) => match k.parse() {
+                Ok(k) if k > 0 => Ok(Sampling::TopK(k)),
+                _ => Err(invalid()),
+            },
+            Some(("topp", p)) => match p.parse() {
+                Ok(p) if p > 0.0 && p <= 1.0 => Ok(Sampling::TopP(p)),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// a word that may be drawn, and the context it leads to
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Step {
+    pub(crate) word: u32,
+    pub(crate) context: Option<u32>,
+}
+
+// how likely words are regardless of the guide, relative to the best
+// guided word, so that sentences can still go the long way round
+const UNGUIDED: f64 = 0.05;
+
+// Vose's alias method, see https://www.keithschwarz.com/darts-dice-coins/
+#[derive(Clone, Debug)]
+struct AliasTable {
+    steps: Vec<Step>,
+    weights: Vec<f64>,
+    prob: Vec<f64>,
+    alias: Vec<usize>,
+    total: i32,
+}
+
+impl<'a> Sampler<'a> {
+    pub fn new(stats: &'a Stats, temperature: f64, skip_unknown: bool) -> Self {
+        let config = GeneratorConfig {
+            temperature,
+            skip_unknown,
+            ..GeneratorConfig::default()
+        };
+        Sampler::with_config(stats, &config)
+    }
+
+    /// Creates a sampler with the temperature, smoothing and handling of
+    /// unknown words of `config`.
+    pub fn with_config(stats: &'a Stats, config: &GeneratorConfig) -> Self {
+        let unknown = stats.words.id(UNK).filter(|_| config.skip_unknown);
+        let mut tables = HashMap::with_capacity(stats.of.len());
+        let mut vocabulary = HashSet::new();
+        for (&context, stat) in stats.of.iter() {
+            let mut candidates: Vec<(&str, i32, Step)> = stat.next
+                .iter()
+                .filter(|&(&word, _)| Some(word) != unknown)
+                .map(|(&word, &number)| {
+                    let step = Step {
+                        word,
+                        context: Sampler::advance(stats, context, word),
+                    };
+                    (stats.words.get(word), number, step)
+                })
+                .collect();
+            if candidates.is_empty() {
+                continue;
+            }
+            vocabulary.extend(candidates.iter().map(|&(_, _, step)| step.word));
+            // sort candidates so a seeded rng always picks the same word
+            candidates.sort_by_key(|&(word, _, _)| word);
+            let candidates = candidates
+                .into_iter()
+                .map(|(_, number, step)| (number, step))
+                .collect();
+            tables.insert(context, AliasTable::new(candidates, config.temperature, config.sampling));
+        }
+        let mut vocabulary: Vec<u32> = vocabulary.into_iter().collect();
+        vocabulary.sort_by_key(|&word| stats.words.get(word));
+        let backoff = match config.backoff {
+            true => Sampler::backoff(stats, unknown, config),
+            false => HashMap::new(),
+        };
+        Sampler {
+            stats,
+            tables,
+            end: stats.words.id(END),
+            smoothing: config.smoothing,
+            vocabulary,
+            backoff,
+        }
+    }
+
+    // sums up the counts of every context sharing the same last words,
+    // for each of their lengths below the order of the model
+    fn backoff(stats: &Stats, unknown: Option<u32>, config: &GeneratorConfig) -> HashMap<String, AliasTable> {
+        let mut counts: HashMap<String, HashMap<u32, i32>> = HashMap::new();
+        for (&context, stat) in stats.of.iter() {
+            let words: Vec<&str> = stats.words.get(context).split(' ').collect();
+            for skip in 1..words.len() {
+                let next = counts.entry(words[skip..].join(" ")).or_default();
+                for (&word, &number) in &stat.next {
+                    *next.entry(word).or_insert(0) += number;
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .filter_map(|(context, next)| {
+                let mut candidates: Vec<(i32, Step)> = next
+                    .into_iter()
+                    .filter(|&(word, _)| Some(word) != unknown)
+                    .map(|(word, number)| (number, Step { word, context: None }))
+                    .collect();
+                if candidates.is_empty() {
+                    return None;
+                }
+                candidates.sort_by_key(|&(_, step)| stats.words.get(step.word));
+                Some((context, AliasTable::new(candidates, config.temperature, config.sampling)))
+            })
+            .collect()
+    }
+
+    /// The model this sampler draws from.
+    pub fn stats(&self) -> &'a Stats {
+        self.stats
+    }
+
+    /// Draws the word following `context`, if it has any.
+    pub fn sample<R: Rng>(&self, context: &str, rng: &mut R) -> Option<&'a str> {
+        let context = self.stats.words.id(context)?;
+        self.step(context, rng)
+            .map(|step| self.stats.words.get(step.word))
+    }
+
+    pub(crate) fn step<R: Rng>(&self, context: u32, rng: &mut R) -> Option<Step> {
+        let table = self.tables.get(&context);
+        if self.smoothing > 0.0 && !self.vocabulary.is_empty() {
+            // smoothed counts are either seen counts or the uniform
+            // share of the vocabulary, in proportion to their totals
+            let total = table.map_or(0, |table| table.total) as f64;
+            let unseen = self.smoothing * self.vocabulary.len() as f64;
+            if rng.gen::<f64>() * (total + unseen) < unseen {
+                let word = self.vocabulary[rng.gen_range(0, self.vocabulary.len())];
+                return Some(Step {
+                    word,
+                    context: Sampler::advance(self.stats, context, word),
+                });
+            }
+        }
+        table.map(|table| table.sample(rng))
+    }
+
+    pub(crate) fn backs_off(&self) -> bool {
+        !self.backoff.is_empty()
+    }
+
+    // draws the word following the longest of the last words of `context`
+    // some context of the model ends with, for contexts it has not seen
+    pub(crate) fn back_off<R: Rng>(&self, context: &str, rng: &mut R) -> Option<Step> {
+        let mut words = context;
+        while let Some((_, shorter)) = words.split_once(' ') {
+            words = shorter;
+            if let Some(table) = self.backoff.get(words) {
+                let mut step = table.sample(rng);
+                let next = self.stats.advance(context, self.stats.words.get(step.word));
+                step.context = self.stats.words.id(&next).filter(|next| self.tables.contains_key(next));
+                return Some(step);
+            }
+        }
+        None
+    }
+
+    // draws the word following `context` in proportion to both its own
+    // probability and how much `guide` favors it, without smoothing
+    pub(crate) fn steer<R: Rng>(&self, context: u32, guide: &HashMap<u32, f64>, rng: &mut R) -> Option<Step> {
+        let table = self.tables.get(&context)?;
+        let guidance: Vec<f64> = table.steps
+            .iter()
+            .map(|step| guide.get(&step.word).copied().unwrap_or(0.0))
+            .collect();
+        let best = guidance.iter().copied().fold(0.0, f64::max);
+        if best == 0.0 {
+            return Some(table.sample(rng));
+        }
+
+        let weights: Vec<f64> = table.weights
+            .iter()
+            .zip(&guidance)
+            .map(|(weight, guidance)| weight * (guidance / best + UNGUIDED))
+            .collect();
+        let mut pick = rng.gen::<f64>() * weights.iter().sum::<f64>();
+        for (step, weight) in table.steps.iter().zip(weights) {
+            if pick < weight {
+                return Some(*step);
+            }
+            pick -= weight;
+        }
+        table.steps.last().copied()
+    }
+
+    fn advance(stats: &Stats, context: u32, word: u32) -> Option<u32> {
+        if stats.order == 1 {
+            return Some(word);
+        }
+        let context = stats.advance(stats.words.get(context), stats.words.get(word));
+        stats.words.id(&context)
+    }
+}
+
+impl AliasTable {
+    fn new(candidates: Vec<(i32, Step)>, temperature: f64, sampling: Sampling) -> Self {
+        let candidates = restrict(candidates, sampling);
+        // reshape the counts with the temperature, relative to the most
+        // frequent candidate so tiny temperatures cannot overflow
+        let max = candidates.iter().map(|&(number, _)| number).max().unwrap_or(1);
+        let weights: Vec<f64> = candidates
+            .iter()
+            .map(|&(number, _)| match sampling {
+                Sampling::Softmax => (f64::from(number - max) / temperature).exp(),
+                _ => ((f64::from(number).ln() - f64::from(max).ln()) / temperature).exp(),
+            })
+            .collect();
+
+        let n = weights.len();
+        let total: f64 = weights.iter().sum();
+        let mut scaled: Vec<f64> = weights
+            .iter()
+            .map(|weight| weight * n as f64 / total)
+            .collect();
+
+        let mut small = Vec::new();
+        let mut large = Vec::new();
+        for (i, &p) in scaled.iter().enumerate() {
+            if p < 1.0 {
+                small.push(i);
+            } else {
+                large.push(i);
+            }
+        }
+
+        let mut prob = vec![1.0; n];
+        let mut alias: Vec<usize> = (0..n).collect();
+        while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+            small.pop();
+            large.pop();
+            prob[s] = scaled[s];
+            alias[s] = l;
+            scaled[l] += scaled[s] - 1.0;
+            if scaled[l] < 1.0 {
+                small.push(l);
+            } else {
+                large.push(l);
+            }
+        }
+
+        let total = candidates.iter().map(|&(number, _)| number).sum();
+        let steps = candidates
+            .into_iter()
+            .map(|(_, step)| step)
+            .collect();
+        AliasTable { steps, weights, prob, alias, total }
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> Step {
+        let i = rng.gen_range(0, self.steps.len());
+        if rng.gen::<f64>() < self.prob[i] {
+            self.steps[i]
+        } else {
+            self.steps[self.alias[i]]
+        }
+    }
+}
+
+// keeps the most frequent candidates only, when sampling among them
+fn restrict(mut candidates: Vec<(i32, Step)>, sampling: Sampling) -> Vec<(i32, Step)> {
+    let keep = match sampling {
+        Sampling::Uniform | Sampling::Softmax => return candidates,
+        Sampling::TopK(k) => k,
+        Sampling::TopP(p) => {
+            let total: i64 = candidates.iter().map(|&(number, _)| i64::from(number)).sum();
+            let wanted = total as f64 * p;
+            let mut numbers: Vec<i32> = candidates.iter().map(|&(number, _)| number).collect();
+            numbers.sort_unstable_by(|a, b| b.cmp(a));
+            let mut seen = 0;
+            numbers
+                .iter()
+                .take_while(|&&number| {
+                    let short = (seen as f64) < wanted;
+                    seen += i64::from(number);
+                    short
+                })
+                .count()
+        },
+    };
+    // stable, so candidates seen equally often stay in the order of
+    // their words
+    candidates.sort_by(|(a, _), (b, _)| b.cmp(a));
+    candidates.truncate(keep.max(1));
+    candidates
+}
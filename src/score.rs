@@ -0,0 +1,59 @@
+use std::collections::HashSet;
+
+use crate::Stats;
+
+/// How likely a text is under a trained model.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Score {
+    /// Natural logarithm of the probability of the whole text.
+    pub log_prob: f64,
+    /// How many tokens were predicted, counting the end of each sentence.
+    pub words: usize,
+}
+
+impl Score {
+    /// The perplexity per word, lower meaning the text fits the model
+    /// better.
+    pub fn perplexity(&self) -> f64 {
+        (-self.log_prob / self.words as f64).exp()
+    }
+}
+
+/// Scores every line of a text against `stats`.
+///
+/// Lines are split into sentences just like during training. Every
+/// count gets `smoothing` added to it, so that with any `smoothing` above
+/// 0 no text is ever impossible.
+pub fn score<I, S>(stats: &Stats, lines: I, smoothing: f64) -> Score
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    // every word that may follow some context
+    let vocabulary: HashSet<u32> = stats.of
+        .values()
+        .flat_map(|stat| stat.next.keys().copied())
+        .collect();
+    let vocabulary = vocabulary.len() as f64;
+
+    let tokenizer = stats.tokenizer();
+    let mut score = Score::default();
+    for line in lines {
+        let sentences = stats.sentences(line.as_ref(), &tokenizer)
+            .into_iter()
+            .filter_map(|tokens| stats.frame(tokens));
+        for words in sentences {
+            for window in words.windows(stats.order + 1) {
+                let (context, neigh) = window.split_at(stats.order);
+                let context = context.join(" ");
+                let total = stats.get(&context).map_or(0, |stat| stat.total());
+                let number = stats.count(&context, &neigh[0]);
+                let prob = (number as f64 + smoothing)
+                    / (total as f64 + smoothing * vocabulary);
+                score.log_prob += prob.ln();
+                score.words += 1;
+            }
+        }
+    }
+    score
+}
@@ -0,0 +1,1290 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{hash_map, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{self, BufRead};
+
+use permutation::permutation;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use regex::Regex;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::interner::Interner;
+use crate::tokenizer::{Builtin, Separators, Tokenizer};
+use crate::{normalize, GeneratorConfig, Normalization, Sampler, Usage};
+
+/// Marks the beginning of a sentence in a context.
+pub const START: &str = "<START>";
+
+/// Marks the end of a sentence, generation stops when it is drawn.
+pub const END: &str = "<END>";
+
+/// Stands for every word left out of a limited vocabulary.
+pub const UNK: &str = "<UNK>";
+
+/// Stands for every number, when they are collapsed.
+pub const NUM: &str = "<NUM>";
+
+// bumped whenever saved models cannot be read the same way anymore
+const VERSION: u32 = 2;
+
+/// Transition counts for every context seen during training.
+///
+/// A context is made of the previous `order` words, joined by a single
+/// space; with the default order of 1 it is just the previous word. Every
+/// line is framed by [`START`] and [`END`], so sentences start from the
+/// [`start`](Stats::start) context and end when [`END`] follows.
+///
+/// Words and contexts are only stored once, everything else refers to
+/// them by id.
+///
+/// Models are serialized as a JSON object of their settings, and of the
+/// counts of every neighbor of every context:
+///
+/// ```json
+/// {
+///   "version": 2,
+///   "order": 1,
+///   "tokens": "words",
+///   "normalization": { "form": "nfkc", "lowercase": true },
+///   "reverse": false,
+///   "token_regex": null,
+///   "of": {
+///     "<START>": { "next": { "the": 2 } },
+///     "the": { "next": { "cat": 1, "dog": 1 } },
+///     "cat": { "next": { "<END>": 1 } },
+///     "dog": { "next": { "<END>": 1 } }
+///   },
+///   "forms": {
+///     "the": { "The": 1, "the": 1 }
+///   }
+/// }
+/// ```
+///
+/// Only `order` and `of` are required, the settings default to the ones
+/// of [`TrainConfig`]. The `token_regex` is `null` unless one was used.
+/// The `forms` are how often each word was spelled each way before it
+/// was normalized, for words that were not always spelled as they are
+/// normalized. Models of a later `version` than the current one fail to
+/// load, those without one are taken to be current, and those of version
+/// 1 are the ones without `forms`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "RawStats")]
+pub struct Stats {
+    pub order: usize,
+    pub tokens: Tokens,
+    pub normalization: Normalization,
+    /// Whether sentences were learned backwards, so that each context
+    /// maps to the words that preceded it.
+    pub reverse: bool,
+    /// What words look like, when they are not just separated by
+    /// whitespace.
+    pub token_regex: Option<Regex>,
+    pub(crate) words: Interner,
+    pub(crate) of: HashMap<u32, Stat>,
+    vocabulary: Option<HashSet<String>>,
+    self_loops: bool,
+    filters: Filters,
+    separators: Option<Separators>,
+    reading: Reading,
+    revision: u64,
+    // the contexts changed or dropped in each revision, oldest first,
+    // once or more each
+    changes: Vec<(u64, u32)>,
+    // how often each normalized word was spelled some other way
+    forms: HashMap<String, HashMap<String, i32>>,
+    // how often each word was learned however it was spelled, by id
+    seen: Vec<i32>,
+}
+
+/// What a model is made of.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tokens {
+    /// Whitespace separated words.
+    #[default]
+    Words,
+    /// Whitespace separated words, with punctuation split off into
+    /// tokens of its own.
+    Punct,
+    /// The characters of each word, so that every word is a sentence of
+    /// its own and generation makes up new words.
+    Chars,
+}
+
+/// What to do with tokens made only of digits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Numbers {
+    /// Learn every number as a word of its own.
+    #[default]
+    Keep,
+    /// Learn every number as [`NUM`], keeping the vocabulary small.
+    Collapse,
+    /// Leave numbers out, so the tokens around them follow each other.
+    Drop,
+}
+
+/// What a model learns as a sentence, framed by [`START`] and [`END`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Segment {
+    /// Every line is a sentence, as in lists of titles or of verses.
+    #[default]
+    Line,
+    /// Lines are joined again and split after every `.`, `!` or `?`, so
+    /// that prose broken at arbitrary columns is learned by its sentences.
+    /// Blank lines end sentences as well, like those of headings.
+    Sentence,
+}
+
+// what happens to tokens after they are normalized, only while training
+#[derive(Clone, Debug, Default)]
+struct Filters {
+    numbers: Numbers,
+    stopwords: HashSet<String>,
+    min_len: usize,
+}
+
+// how lines are read before splitting them, only while training
+#[derive(Clone, Copy, Debug, Default)]
+struct Reading {
+    lossy: bool,
+    strip_html: bool,
+    segment: Segment,
+}
+
+/// Settings used to train a model.
+#[derive(Clone, Debug)]
+pub struct TrainConfig {
+    /// How many tokens of context each transition has.
+    pub order: usize,
+    pub tokens: Tokens,
+    pub normalization: Normalization,
+    /// Learn every sentence backwards, so generated ones come out
+    /// reversed.
+    pub reverse: bool,
+    /// Take every match of this regex as a word, instead of splitting
+    /// lines on whitespace.
+    pub token_regex: Option<Regex>,
+    /// Keep only this many of the most frequent words, folding the rest
+    /// into [`UNK`]. Since every line needs to be seen first, they are
+    /// all buffered in memory.
+    pub vocab: Option<usize>,
+    /// How many threads to train with, 0 meaning one per core. Training
+    /// with more than one buffers every line in memory, the result is the
+    /// same either way.
+    pub threads: usize,
+    /// How much every transition counts for, so that some corpora can
+    /// weigh more than others once merged.
+    pub weight: i32,
+    /// Never learn a word following itself, so that models of stuttering
+    /// corpora cannot stutter either. Unlike
+    /// [`no_repeat`](crate::GeneratorConfig::no_repeat), which only avoids
+    /// recent words while generating, such transitions are not even in
+    /// the model.
+    pub no_self_loops: bool,
+    /// What to do with numbers. Like the other filters of tokens, this is
+    /// not saved along with the model.
+    pub numbers: Numbers,
+    /// Leave these words out, so the words around them follow each other.
+    /// Sentences lose some of their fluency, as articles and prepositions
+    /// are usually the ones left out, but are denser in content.
+    pub stopwords: HashSet<String>,
+    /// Leave out tokens of less than this many characters once
+    /// normalized, like stopwords.
+    pub min_token_len: usize,
+    /// Decode lines that are not valid UTF-8 anyway, replacing what is
+    /// invalid with U+FFFD, instead of failing.
+    pub lossy: bool,
+    /// Remove HTML and XML tags from lines, and decode the entities of
+    /// basic characters like `&amp;`, before splitting them. Tags are
+    /// only removed when they open and close on the same line.
+    pub strip_html: bool,
+    /// How [`read_stats`] and [`Stats::read`] split their input into
+    /// sentences. Generated text starts and ends wherever these did, since
+    /// [`START`] and [`END`] frame each of them. Like the other settings
+    /// of how lines are read, this is not saved along with the model.
+    pub segment: Segment,
+    /// Split words on these characters instead of whitespace, unless a
+    /// `token_regex` is given. Like the filters of tokens, this is not
+    /// saved along with the model.
+    pub split_on: Option<Separators>,
+}
+
+/// How many times each neighbor followed a given context.
+#[derive(Clone, Debug, Default)]
+pub struct Stat {
+    pub(crate) next: HashMap<u32, i32>,
+}
+
+/// The neighbors of a context, along with how many times each was seen.
+#[derive(Clone, Debug)]
+pub struct Next<'a> {
+    words: &'a Interner,
+    iter: hash_map::Iter<'a, u32, i32>,
+}
+
+// how models look like on disk
+#[derive(Serialize)]
+struct StatsRef<'a> {
+    version: u32,
+    order: usize,
+    tokens: Tokens,
+    normalization: Normalization,
+    reverse: bool,
+    // always written, binary formats cannot tell missing fields apart
+    token_regex: Option<&'a str>,
+    of: HashMap<&'a str, StatRef<'a>>,
+    forms: HashMap<&'a str, HashMap<&'a str, i32>>,
+}
+
+#[derive(Serialize)]
+struct StatRef<'a> {
+    next: HashMap<&'a str, i32>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct RawStats {
+    #[serde(default = "current_version")]
+    version: u32,
+    order: usize,
+    #[serde(default)]
+    tokens: Tokens,
+    #[serde(default)]
+    normalization: Normalization,
+    #[serde(default)]
+    reverse: bool,
+    #[serde(default)]
+    token_regex: Option<String>,
+    of: HashMap<String, RawStat>,
+    #[serde(default)]
+    forms: HashMap<String, HashMap<String, i32>>,
+}
+
+// how models of version 1 look like on disk, which binary formats need
+// spelled out as they cannot tell missing fields apart
+#[derive(Deserialize)]
+pub(crate) struct RawStatsV1 {
+    version: u32,
+    order: usize,
+    tokens: Tokens,
+    normalization: Normalization,
+    reverse: bool,
+    token_regex: Option<String>,
+    of: HashMap<String, RawStat>,
+}
+
+#[derive(Deserialize)]
+struct RawStat {
+    next: HashMap<String, i32>,
+}
+
+impl From<RawStatsV1> for RawStats {
+    fn from(raw: RawStatsV1) -> Self {
+        RawStats {
+            version: raw.version,
+            order: raw.order,
+            tokens: raw.tokens,
+            normalization: raw.normalization,
+            reverse: raw.reverse,
+            token_regex: raw.token_regex,
+            of: raw.of,
+            forms: HashMap::new(),
+        }
+    }
+}
+
+/// Orders the neighbors of each context from the most to the least often
+/// seen, along with how many times that was.
+///
+/// Neighbors seen equally often are ordered by the words themselves, so
+/// the order is the same on every run.
+pub fn determine_highest_usage(stats: &Stats) -> HashMap<String, Vec<(String, i32)>> {
+    let mut usage = HashMap::new();
+    for (context, neighbors) in stats.contexts() {
+        usage.insert(context.to_string(), order_by_usage(neighbors));
+    }
+    usage
+}
+
+/// Keeps [`determine_highest_usage`] of a model up to date as it keeps
+/// training, ordering again only the contexts that changed since.
+///
+/// ```
+/// use papagaio::{Stats, TrainConfig, UsageCache};
+///
+/// let config = TrainConfig::default();
+/// let mut stats = Stats::train(vec!["a b", "c d"], &config);
+/// let mut cache = UsageCache::new(&stats);
+///
+/// // only the start context, "a" and "c" change
+/// stats.observe("a c", &config);
+/// assert_eq!(cache.refresh(&stats), 3);
+/// assert_eq!(cache.usage()["a"], [("b".to_string(), 1), ("c".to_string(), 1)]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct UsageCache {
+    usage: HashMap<String, Vec<(String, i32)>>,
+    // the revision of the model last refreshed from
+    revision: u64,
+}
+
+impl UsageCache {
+    pub fn new(stats: &Stats) -> Self {
+        let mut cache = UsageCache::default();
+        cache.refresh(stats);
+        cache
+    }
+
+    /// Orders the neighbors of every context of `stats` that changed
+    /// since the last refresh, returning how many there were.
+    ///
+    /// The cache is meant for a single model, refreshing it with another
+    /// one may leave it out of date.
+    pub fn refresh(&mut self, stats: &Stats) -> usize {
+        let since = stats.changes.partition_point(|&(revision, _)| revision <= self.revision);
+        let mut seen = HashSet::new();
+        let mut changed = 0;
+        for &(_, id) in &stats.changes[since..] {
+            if !seen.insert(id) {
+                continue;
+            }
+            let context = stats.words.get(id);
+            match stats.of.get(&id) {
+                Some(stat) => {
+                    changed += 1;
+                    self.usage.insert(context.to_string(), order_by_usage(stats.neighbors(stat)));
+                },
+                // pruned contexts are gone from the model
+                None => {
+                    self.usage.remove(context);
+                },
+            }
+        }
+        self.revision = stats.revision;
+        changed
+    }
+
+    /// The neighbors of every context, ordered like
+    /// [`determine_highest_usage`].
+    pub fn usage(&self) -> &HashMap<String, Vec<(String, i32)>> {
+        &self.usage
+    }
+}
+
+fn order_by_usage(neighbors: Next<'_>) -> Vec<(String, i32)> {
+    let mut keys = Vec::new();
+    let mut words = Vec::new();
+    for (neigh, number) in neighbors {
+        keys.push((Reverse(number), neigh));
+        words.push((neigh.to_string(), number));
+    }
+    let perm = permutation::sort(keys);
+    perm.apply_slice(words)
+}
+
+/// Trains a model from every line of `reader`.
+pub fn read_stats<R: BufRead>(reader: R, config: &TrainConfig) -> io::Result<Stats> {
+    let tokenizer = Stats::with_config(config).tokenizer();
+    read_stats_with(reader, config, &tokenizer)
+}
+
+/// Trains a model from every line of `reader`, split by `tokenizer`
+/// instead of the one `config` describes.
+///
+/// Only the settings of `config` are saved along with the model, scoring
+/// and picking start words go by them.
+pub fn read_stats_with<R: BufRead>(reader: R, config: &TrainConfig, tokenizer: &dyn Tokenizer) -> io::Result<Stats> {
+    let reading = Stats::with_config(config).reading;
+    let mut error = None;
+    let stats = Stats::train_with(sentences(reader, reading, &mut error), config, tokenizer);
+    match error {
+        Some(e) => Err(e),
+        None => Ok(stats),
+    }
+}
+
+// the sentences of `reader`, which stop at the first line that cannot be
+// read, leaving why in `error`
+fn sentences<'r, R: BufRead + 'r>(
+    reader: R,
+    reading: Reading,
+    error: &'r mut Option<io::Error>,
+) -> Box<dyn Iterator<Item = String> + 'r> {
+    let lines = reader
+        .split(b'\n')
+        .enumerate()
+        .map(move |(i, line)| {
+            let mut line = decode(line?, i + 1, reading.lossy)?;
+            // files saved by some editors start with a byte order mark
+            if i == 0 && line.starts_with('\u{feff}') {
+                line.drain(..'\u{feff}'.len_utf8());
+            }
+            Ok(line)
+        })
+        .map(move |line| match reading.strip_html {
+            true => line.map(|line| strip_html(&line)),
+            false => line,
+        })
+        .map_while(move |line| line.map_err(|e| *error = Some(e)).ok());
+    match reading.segment {
+        Segment::Line => Box::new(lines),
+        Segment::Sentence => Box::new(split_sentences(lines)),
+    }
+}
+
+fn decode(line: Vec<u8>, number: usize, lossy: bool) -> io::Result<String> {
+    match String::from_utf8(line) {
+        Ok(line) => Ok(line),
+        Err(e) if lossy => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        Err(e) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line {} is not valid UTF-8: {}", number, e.utf8_error()),
+        )),
+    }
+}
+
+// contexts are words joined by spaces, so the spaces that tokenizers
+// leave within tokens are learned as no-break spaces, and shown as they
+// were written
+fn escape(token: String) -> String {
+    match token.contains(' ') {
+        true => token.replace(' ', "\u{a0}"),
+        false => token,
+    }
+}
+
+// joins lines back together and splits them again after terminators
+fn split_sentences<I: Iterator<Item = String>>(mut lines: I) -> impl Iterator<Item = String> {
+    let mut buffer = String::new();
+    // everything before this was already searched for a sentence end
+    let mut searched = 0;
+    std::iter::from_fn(move || loop {
+        if let Some(end) = sentence_end(&buffer[searched..]) {
+            let end = searched + end;
+            let rest = buffer[end..].trim_start().to_string();
+            let mut sentence = std::mem::replace(&mut buffer, rest);
+            sentence.truncate(end);
+            searched = 0;
+            return Some(sentence);
+        }
+        searched = buffer.len();
+        match lines.next() {
+            Some(line) if line.trim().is_empty() => if !buffer.trim().is_empty() {
+                searched = 0;
+                return Some(std::mem::take(&mut buffer));
+            },
+            Some(line) => {
+                buffer.push_str(line.trim_end());
+                buffer.push(' ');
+            },
+            None if buffer.trim().is_empty() => return None,
+            None => {
+                searched = 0;
+                return Some(std::mem::take(&mut buffer));
+            },
+        }
+    })
+}
+
+// where the first sentence of `text` ends, after its terminators and any
+// closing quotes or brackets, as long as whitespace follows them
+fn sentence_end(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+        while let Some(&(_, c)) = chars.peek() {
+            match c {
+                '.' | '!' | '?' | '"' | '\'' | ')' | ']' | '”' | '’' | '»' => { chars.next(); },
+                _ => break,
+            }
+        }
+        match chars.peek() {
+            Some(&(at, c)) if c.is_whitespace() => return Some(at),
+            _ => (),
+        }
+    }
+    None
+}
+
+// replaces tags with spaces, so that words around them stay apart
+fn strip_html(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(open) = rest.find('<') {
+        let close = match rest[open..].find('>') {
+            Some(close) => open + close,
+            None => break,
+        };
+        text.push_str(&rest[..open]);
+        text.push(' ');
+        rest = &rest[close + 1..];
+    }
+    text.push_str(rest);
+    decode_entities(&text)
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        decoded.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 8)
+            .and_then(|end| entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            },
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)
+        },
+    }
+}
+
+impl Filters {
+    // what a normalized token is learned as, if at all
+    fn apply(&self, token: String) -> Option<String> {
+        if self.stopwords.contains(&token) || token.chars().count() < self.min_len {
+            return None;
+        }
+        let number = !token.is_empty() && token.chars().all(|c| c.is_ascii_digit());
+        match self.numbers {
+            Numbers::Collapse if number => Some(NUM.to_string()),
+            Numbers::Drop if number => None,
+            _ => Some(token),
+        }
+    }
+}
+
+impl Stat {
+    pub fn new() -> Self {
+        Stat::default()
+    }
+
+    /// How many distinct neighbors were seen.
+    pub fn len(&self) -> usize {
+        self.next.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.next.is_empty()
+    }
+
+    /// How many transitions were seen in total.
+    pub fn total(&self) -> i32 {
+        self.next.values().sum()
+    }
+
+    /// The Shannon entropy of the neighbors, in bits. It is 0 when the
+    /// same neighbor always follows, and grows with how unpredictable the
+    /// next one is.
+    pub fn entropy(&self) -> f64 {
+        let total = f64::from(self.total());
+        self.next
+            .values()
+            .map(|&number| f64::from(number) / total)
+            .filter(|&p| p > 0.0)
+            .map(|p| -p * p.log2())
+            .sum()
+    }
+}
+
+impl<'a> Iterator for Next<'a> {
+    type Item = (&'a str, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next()
+            .map(|(&word, &number)| (self.words.get(word), number))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Next<'_> {}
+
+impl Serialize for Stats {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let of = self
+            .contexts()
+            .map(|(context, next)| (context, StatRef { next: next.collect() }))
+            .collect();
+        // words only ever spelled as they are normalized go without saying
+        let forms = self.forms
+            .iter()
+            .map(|(word, forms)| {
+                let mut forms: HashMap<&str, i32> = forms
+                    .iter()
+                    .map(|(form, &number)| (form.as_str(), number))
+                    .collect();
+                let same = self.same(word);
+                if same > 0 {
+                    forms.insert(word, same);
+                }
+                (word.as_str(), forms)
+            })
+            .collect();
+        let stats = StatsRef {
+            version: VERSION,
+            order: self.order,
+            tokens: self.tokens,
+            normalization: self.normalization,
+            reverse: self.reverse,
+            token_regex: self.token_regex.as_ref().map(Regex::as_str),
+            of,
+            forms,
+        };
+        stats.serialize(serializer)
+    }
+}
+
+fn current_version() -> u32 {
+    VERSION
+}
+
+// fails for models of a later version than this one can read
+pub(crate) fn check_version(version: u32) -> Result<(), String> {
+    if version > VERSION {
+        return Err(format!(
+            "model has version {}, but only versions up to {} are supported",
+            version, VERSION,
+        ));
+    }
+    Ok(())
+}
+
+impl PartialEq for Stats {
+    // ids depend on the order words were seen in, so compare the words,
+    // and surface forms only change how they are shown
+    fn eq(&self, other: &Self) -> bool {
+        let same_regex = self.token_regex.as_ref().map(Regex::as_str)
+            == other.token_regex.as_ref().map(Regex::as_str);
+        let same_counts = self.of.len() == other.of.len()
+            && self.contexts().all(|(context, mut next)| {
+                other.get(context).is_some_and(|stat| stat.len() == next.len())
+                    && next.all(|(neigh, number)| other.count(context, neigh) == number)
+            });
+        self.order == other.order
+            && self.tokens == other.tokens
+            && self.normalization == other.normalization
+            && self.reverse == other.reverse
+            && same_regex
+            && same_counts
+    }
+}
+
+impl TryFrom<RawStats> for Stats {
+    type Error = String;
+
+    fn try_from(raw: RawStats) -> Result<Self, Self::Error> {
+        check_version(raw.version)?;
+        let token_regex = raw.token_regex
+            .map(|regex| Regex::new(&regex))
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let mut stats = Stats {
+            order: raw.order,
+            tokens: raw.tokens,
+            normalization: raw.normalization,
+            reverse: raw.reverse,
+            token_regex,
+            words: Interner::default(),
+            of: HashMap::new(),
+            vocabulary: None,
+            self_loops: true,
+            filters: Filters::default(),
+            separators: None,
+            reading: Reading::default(),
+            revision: 0,
+            changes: Vec::new(),
+            forms: HashMap::new(),
+            seen: Vec::new(),
+        };
+        for (context, stat) in raw.of {
+            for (neigh, number) in stat.next {
+                stats.add(&context, &neigh, number);
+            }
+        }
+        for (word, forms) in raw.forms {
+            for (form, number) in forms {
+                stats.spell(&word, &form, number);
+            }
+        }
+        Ok(stats)
+    }
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        TrainConfig {
+            order: 1,
+            tokens: Tokens::Words,
+            normalization: Normalization::default(),
+            reverse: false,
+            token_regex: None,
+            vocab: None,
+            threads: 1,
+            weight: 1,
+            no_self_loops: false,
+            numbers: Numbers::Keep,
+            stopwords: HashSet::new(),
+            min_token_len: 0,
+            lossy: false,
+            strip_html: false,
+            segment: Segment::Line,
+            split_on: None,
+        }
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats::new()
+    }
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Stats::with_config(&TrainConfig::default())
+    }
+
+    /// Creates an empty model to be trained with `config`.
+    pub fn with_config(config: &TrainConfig) -> Self {
+        Stats {
+            order: config.order,
+            tokens: config.tokens,
+            normalization: config.normalization,
+            reverse: config.reverse,
+            token_regex: config.token_regex.clone(),
+            words: Interner::default(),
+            of: HashMap::new(),
+            vocabulary: None,
+            self_loops: !config.no_self_loops,
+            filters: Filters {
+                numbers: config.numbers,
+                stopwords: config.stopwords
+                    .iter()
+                    .map(|word| normalize(word, &config.normalization))
+                    .collect(),
+                min_len: config.min_token_len,
+            },
+            separators: config.split_on.clone(),
+            reading: Reading {
+                lossy: config.lossy,
+                strip_html: config.strip_html,
+                segment: config.segment,
+            },
+            revision: 0,
+            changes: Vec::new(),
+            forms: HashMap::new(),
+            seen: Vec::new(),
+        }
+    }
+
+    /// Trains a model from an iterator of lines.
+    ///
+    /// ```
+    /// use papagaio::{Stats, TrainConfig};
+    ///
+    /// let lines = vec!["the cat sat", "the dog ran"];
+    /// let stats = Stats::train(lines, &TrainConfig::default());
+    /// assert_eq!(stats.count("the", "cat"), 1);
+    /// assert_eq!(stats.count("the", "dog"), 1);
+    /// ```
+    pub fn train<I, S>(lines: I, config: &TrainConfig) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let tokenizer = Stats::with_config(config).tokenizer();
+        Stats::train_with(lines, config, &tokenizer)
+    }
+
+    /// Trains a model from an iterator of lines, split by `tokenizer`
+    /// instead of the one `config` describes.
+    pub fn train_with<I, S>(lines: I, config: &TrainConfig, tokenizer: &dyn Tokenizer) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut stats = Stats::with_config(config);
+        if config.vocab.is_none() && config.threads == 1 {
+            for line in lines {
+                stats.learn(line.as_ref(), tokenizer, config.weight);
+            }
+            return stats;
+        }
+
+        // owned, so they can be shared between threads whatever they were
+        let lines: Vec<String> = lines
+            .into_iter()
+            .map(|line| line.as_ref().to_string())
+            .collect();
+        if let Some(size) = config.vocab {
+            stats.limit_vocabulary(lines.iter().map(AsRef::as_ref), size, tokenizer);
+        }
+        let pool = match config.threads {
+            1 => None,
+            threads => ThreadPoolBuilder::new().num_threads(threads).build().ok(),
+        };
+        match pool {
+            Some(pool) => {
+                // each chunk is learned on its own, then they are all summed up
+                let chunk = (lines.len() / (pool.current_num_threads() * 4)).max(1);
+                let learned = pool.install(|| lines
+                    .par_chunks(chunk)
+                    .map(|chunk| {
+                        let mut learned = stats.empty();
+                        for line in chunk {
+                            learned.learn(line.as_ref(), tokenizer, config.weight);
+                        }
+                        learned
+                    })
+                    .reduce(|| stats.empty(), |mut a, b| {
+                        a.merge(b);
+                        a
+                    }));
+                stats.merge(learned);
+            },
+            None => for line in lines {
+                stats.learn(line.as_ref(), tokenizer, config.weight);
+            },
+        }
+        stats
+    }
+
+    /// Adds every transition count of `other` to this model.
+    ///
+    /// Both models are expected to share the same settings.
+    pub fn merge(&mut self, mut other: Stats) {
+        // fewer words to look up when merging the smaller one
+        if other.of.len() > self.of.len() {
+            std::mem::swap(&mut self.of, &mut other.of);
+            std::mem::swap(&mut self.words, &mut other.words);
+            std::mem::swap(&mut self.seen, &mut other.seen);
+            // the ids changed along with the words, so everything did
+            self.revision = self.revision.max(other.revision) + 1;
+            let changes = std::mem::take(&mut self.changes);
+            let contexts: Vec<u32> = self.of.keys().copied().collect();
+            for context in contexts {
+                self.touch(context);
+            }
+            for (_, context) in changes {
+                let context = self.words.intern(other.words.get(context));
+                self.touch(context);
+            }
+        }
+        for (context, stat) in other.of {
+            let context = other.words.get(context);
+            for (neigh, number) in stat.next {
+                self.add(context, other.words.get(neigh), number);
+            }
+        }
+        for (id, &number) in other.seen.iter().enumerate() {
+            self.see(other.words.get(id as u32), number);
+        }
+        for (word, forms) in other.forms {
+            let known = self.forms.entry(word).or_default();
+            for (form, number) in forms {
+                *known.entry(form).or_insert(0) += number;
+            }
+        }
+    }
+
+    /// How `word` was most often spelled before it was normalized, or the
+    /// word itself when that is unknown.
+    ///
+    /// ```
+    /// use papagaio::{Stats, TrainConfig};
+    ///
+    /// let stats = Stats::train(vec!["Paris is big", "Paris is old", "paris"], &TrainConfig::default());
+    /// assert_eq!(stats.surface("paris"), "Paris");
+    /// assert_eq!(stats.surface("is"), "is");
+    /// ```
+    pub fn surface<'s>(&'s self, word: &'s str) -> &'s str {
+        let forms = match self.forms.get(word) {
+            Some(forms) => forms,
+            None => return word,
+        };
+        forms
+            .iter()
+            .map(|(form, &number)| (form.as_str(), number))
+            .chain(std::iter::once((word, self.same(word))))
+            .max_by(|(a, x), (b, y)| x.cmp(y).then_with(|| b.cmp(a)))
+            .map_or(word, |(form, _)| form)
+    }
+
+    // how often `word` was spelled as it is normalized
+    fn same(&self, word: &str) -> i32 {
+        let seen = self.words
+            .id(word)
+            .and_then(|id| self.seen.get(id as usize))
+            .copied()
+            .unwrap_or(0);
+        seen - self.forms.get(word).map_or(0, |forms| forms.values().sum())
+    }
+
+    /// Tells whether nothing was learned yet.
+    pub fn is_empty(&self) -> bool {
+        self.of.is_empty()
+    }
+
+    /// Every context seen during training, along with its neighbors.
+    pub fn contexts(&self) -> impl Iterator<Item = (&str, Next<'_>)> + '_ {
+        self.of
+            .iter()
+            .map(move |(&context, stat)| (self.words.get(context), self.neighbors(stat)))
+    }
+
+    /// The neighbors seen right after `context`, if it was ever seen.
+    pub fn next(&self, context: &str) -> Option<Next<'_>> {
+        self.get(context).map(|stat| self.neighbors(stat))
+    }
+
+    /// The statistics of `context`, if it was ever seen.
+    pub fn get(&self, context: &str) -> Option<&Stat> {
+        self.words
+            .id(context)
+            .and_then(|id| self.of.get(&id))
+    }
+
+    /// How many times `neigh` was seen right after `context`.
+    pub fn count(&self, context: &str, neigh: &str) -> i32 {
+        let number = self.get(context)
+            .zip(self.words.id(neigh))
+            .and_then(|(stat, neigh)| stat.next.get(&neigh));
+        number.copied().unwrap_or(0)
+    }
+
+    /// Generates words lazily with every setting of `config`, until the
+    /// caller stops asking for more.
+    ///
+    /// Fails when the start word is not found in the model.
+    pub fn generate(&self, config: &GeneratorConfig) -> Result<impl Iterator<Item = String> + '_, String> {
+        let sampler = Sampler::with_config(self, config);
+        let usage = Usage::configured(Cow::Owned(sampler), config)?;
+        Ok(usage.map(move |word| self.surface(word).to_owned()))
+    }
+
+    /// Keeps training on every line of `reader`, read like [`read_stats`]
+    /// reads them with the config the model was created with.
+    ///
+    /// Transitions never span sentences, so reading several sources is
+    /// the same as reading them one after another.
+    pub fn read<R: BufRead>(&mut self, reader: R) -> io::Result<()> {
+        self.read_with(reader, &self.tokenizer())
+    }
+
+    /// Keeps training on a single line, each of its transitions counting
+    /// [`weight`](TrainConfig::weight) times, so that training can be
+    /// interleaved with generation.
+    ///
+    /// Every other setting is the model's own. Since a [`Sampler`]
+    /// borrows the model, a new one is needed to draw from what was just
+    /// observed, as is a new [`determine_highest_usage`].
+    ///
+    /// ```
+    /// use papagaio::{Stats, TrainConfig};
+    ///
+    /// let config = TrainConfig::default();
+    /// let mut stats = Stats::with_config(&config);
+    /// stats.observe("hello there", &config);
+    /// stats.observe("hello again", &config);
+    /// assert_eq!(stats.next("hello").unwrap().len(), 2);
+    /// ```
+    pub fn observe(&mut self, line: &str, config: &TrainConfig) {
+        self.learn(line, &self.tokenizer(), config.weight);
+    }
+
+    /// Keeps training on every line of `reader`, split by `tokenizer`.
+    pub fn read_with<R: BufRead>(&mut self, reader: R, tokenizer: &dyn Tokenizer) -> io::Result<()> {
+        let mut error = None;
+        for sentence in sentences(reader, self.reading, &mut error) {
+            self.learn(&sentence, tokenizer, 1);
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Records that `neigh` was seen `number` more times right after the
+    /// words in `context`.
+    pub fn update(&mut self, context: &[String], neigh: String, number: i32) {
+        self.add(&context.join(" "), &neigh, number);
+    }
+
+    pub(crate) fn add(&mut self, context: &str, neigh: &str, number: i32) {
+        let context = self.words.intern(context);
+        let neigh = self.words.intern(neigh);
+        self.revision += 1;
+        self.touch(context);
+        let stat = self.of.entry(context).or_default();
+        *stat.next.entry(neigh).or_insert(0) += number;
+    }
+
+    // records that `context` changed in this revision
+    fn touch(&mut self, context: u32) {
+        self.changes.push((self.revision, context));
+        // only the last change of each context matters, and once most are
+        // repeats they are dropped, which keeps recording them cheap
+        if self.changes.len() > 2 * self.words.len() + 1024 {
+            let mut seen = HashSet::new();
+            let mut changes: Vec<(u64, u32)> = self.changes
+                .iter()
+                .rev()
+                .filter(|&&(_, context)| seen.insert(context))
+                .copied()
+                .collect();
+            changes.reverse();
+            self.changes = changes;
+        }
+    }
+
+    fn neighbors<'a>(&'a self, stat: &'a Stat) -> Next<'a> {
+        Next {
+            words: &self.words,
+            iter: stat.next.iter(),
+        }
+    }
+
+    /// Drops every transition seen less than `min_count` times, returning
+    /// how many were dropped.
+    ///
+    /// Contexts left without transitions are dropped as well, so they end
+    /// any sentence that reaches them.
+    pub fn prune(&mut self, min_count: i32) -> usize {
+        let mut pruned = 0;
+        let mut changed = Vec::new();
+        self.revision += 1;
+        for (&context, stat) in self.of.iter_mut() {
+            let before = stat.next.len();
+            stat.next.retain(|_, number| *number >= min_count);
+            if stat.next.len() != before {
+                changed.push(context);
+            }
+            pruned += before - stat.next.len();
+        }
+        self.of.retain(|_, stat| !stat.next.is_empty());
+        for context in changed {
+            self.touch(context);
+        }
+        pruned
+    }
+
+    /// Returns the context reached after `neigh` follows `context`.
+    pub fn advance(&self, context: &str, neigh: &str) -> String {
+        let mut words: Vec<&str> = context.split(' ').collect();
+        words.push(neigh);
+        words[words.len() - self.order..].join(" ")
+    }
+
+    /// Returns the context every sentence starts from.
+    pub fn start(&self) -> String {
+        vec![START; self.order].join(" ")
+    }
+
+    /// Multiplies every transition count by `weight`, so that merging
+    /// favours some models over others.
+    ///
+    /// Counts are rounded, but never down to zero, so weights below 1.5
+    /// leave counts of 1 as they are.
+    pub fn scale(&mut self, weight: f64) {
+        self.revision += 1;
+        let contexts: Vec<u32> = self.of.keys().copied().collect();
+        for context in contexts {
+            self.touch(context);
+        }
+        for stat in self.of.values_mut() {
+            for number in stat.next.values_mut() {
+                *number = ((*number as f64 * weight).round() as i32).max(1);
+            }
+        }
+    }
+
+    // an untrained model with the same settings
+    fn empty(&self) -> Stats {
+        Stats {
+            order: self.order,
+            tokens: self.tokens,
+            normalization: self.normalization,
+            reverse: self.reverse,
+            token_regex: self.token_regex.clone(),
+            words: Interner::default(),
+            of: HashMap::new(),
+            vocabulary: self.vocabulary.clone(),
+            self_loops: self.self_loops,
+            filters: self.filters.clone(),
+            separators: self.separators.clone(),
+            reading: self.reading,
+            revision: 0,
+            changes: Vec::new(),
+            forms: HashMap::new(),
+            seen: Vec::new(),
+        }
+    }
+
+    fn limit_vocabulary<'s>(
+        &mut self,
+        lines: impl Iterator<Item = &'s str>,
+        size: usize,
+        tokenizer: &dyn Tokenizer,
+    ) {
+        let mut counts = HashMap::new();
+        for line in lines {
+            for token in self.sentences(line, tokenizer).into_iter().flatten() {
+                *counts.entry(token).or_insert(0) += 1;
+            }
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+        let vocabulary = counts
+            .into_iter()
+            .take(size)
+            .map(|(token, _)| token)
+            .collect();
+        self.vocabulary = Some(vocabulary);
+    }
+
+    // surrounds a sentence with start and end tokens, unless it is empty
+    pub(crate) fn frame(&self, mut tokens: Vec<String>) -> Option<Vec<String>> {
+        if tokens.is_empty() {
+            return None;
+        }
+        if self.reverse {
+            tokens.reverse();
+        }
+        let mut words: Vec<String> = vec![START.to_string(); self.order];
+        words.extend(tokens.into_iter().map(|token| match self.keeps(&token) {
+            true => token,
+            false => UNK.to_string(),
+        }));
+        words.push(END.to_string());
+        Some(words)
+    }
+
+    // whether `token` is learned as itself, rather than as `UNK`
+    fn keeps(&self, token: &str) -> bool {
+        self.vocabulary.as_ref().is_none_or(|vocabulary| vocabulary.contains(token))
+    }
+
+    pub(crate) fn sentences(&self, line: &str, tokenizer: &dyn Tokenizer) -> Vec<Vec<String>> {
+        self.tokens(line, tokenizer)
+            .into_iter()
+            .map(|tokens| tokens.into_iter().map(|(_, token)| token).collect())
+            .collect()
+    }
+
+    // the sentences of `line`, with every token as it was written and as
+    // it is learned
+    fn tokens<'l>(&self, line: &'l str, tokenizer: &dyn Tokenizer) -> Vec<Vec<(&'l str, String)>> {
+        // lines of CRLF files keep their carriage return
+        let line = line.trim_end_matches(|c: char| c.is_control() || c.is_whitespace());
+        tokenizer
+            .sentences(line)
+            .into_iter()
+            .map(|tokens| tokens
+                .into_iter()
+                .filter_map(|token| {
+                    let normalized = normalize(token, &self.normalization);
+                    self.filters.apply(normalized).map(|normalized| (token, escape(normalized)))
+                })
+                .collect())
+            .collect()
+    }
+
+    // how this model splits lines, unless told otherwise
+    pub(crate) fn tokenizer(&self) -> Builtin {
+        Builtin {
+            tokens: self.tokens,
+            regex: self.token_regex.clone(),
+            separators: self.separators.clone(),
+        }
+    }
+
+    fn learn(&mut self, line: &str, tokenizer: &dyn Tokenizer, weight: i32) {
+        for sentence in self.tokens(line, tokenizer) {
+            // words left out of the vocabulary are only ever UNK
+            for (form, token) in &sentence {
+                if self.keeps(token) {
+                    self.spell(token, form, weight);
+                }
+            }
+            self.learn_sentence(sentence.into_iter().map(|(_, token)| token).collect(), weight);
+        }
+    }
+
+    // counts `form` as a spelling of `token`, unless a filter took it for
+    // something else entirely
+    fn spell(&mut self, token: &str, form: &str, weight: i32) {
+        if token == NUM && form != NUM {
+            return;
+        }
+        self.see(token, weight);
+        if form == token {
+            return;
+        }
+        let forms = match self.forms.get_mut(token) {
+            Some(forms) => forms,
+            None => self.forms.entry(token.to_string()).or_default(),
+        };
+        match forms.get_mut(form) {
+            Some(number) => *number += weight,
+            None => {
+                forms.insert(form.to_string(), weight);
+            },
+        }
+    }
+
+    fn see(&mut self, word: &str, number: i32) {
+        let id = self.words.intern(word) as usize;
+        if self.seen.len() <= id {
+            self.seen.resize(id + 1, 0);
+        }
+        self.seen[id] += number;
+    }
+
+    fn learn_sentence(&mut self, tokens: Vec<String>, weight: i32) {
+        let words = match self.frame(tokens) {
+            Some(words) => words,
+            None => return,
+        };
+        for window in words.windows(self.order + 1) {
+            let (context, neigh) = window.split_at(self.order);
+            if !self.self_loops && context.last() == neigh.first() {
+                continue;
+            }
+            self.update(context, neigh[0].clone(), weight);
+        }
+    }
+}
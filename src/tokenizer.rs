@@ -0,0 +1,134 @@
+//! Splitting lines into tokens.
+//!
+//! A [`Tokenizer`] only splits lines, every token it returns is then
+//! normalized like the rest of the model. Spaces within tokens are
+//! learned as no-break spaces, since a context is its words joined by
+//! spaces.
+
+use regex::Regex;
+
+use crate::Tokens;
+
+/// Splits lines of text into sentences of tokens.
+pub trait Tokenizer: Sync {
+    /// The sentences of `line`, each one a list of tokens.
+    fn sentences<'a>(&self, line: &'a str) -> Vec<Vec<&'a str>>;
+}
+
+/// Whitespace separated words, one sentence per line.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Whitespace;
+
+/// The characters of each whitespace separated word, so that every word
+/// is a sentence of its own.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Chars;
+
+/// Whitespace separated words, with punctuation split off into tokens of
+/// its own.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Punctuation;
+
+/// Words separated by any of the given characters, one sentence per
+/// line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Separators {
+    pub chars: Vec<char>,
+    /// Separate words on whitespace as well, like [`Whitespace`].
+    pub whitespace: bool,
+}
+
+/// Every match of a regex is a token, one sentence per line.
+#[derive(Clone, Debug)]
+pub struct Matches(pub Regex);
+
+// what models are tokenized with, given their settings
+#[derive(Clone, Debug)]
+pub(crate) struct Builtin {
+    pub(crate) tokens: Tokens,
+    pub(crate) regex: Option<Regex>,
+    pub(crate) separators: Option<Separators>,
+}
+
+impl Tokenizer for Whitespace {
+    fn sentences<'a>(&self, line: &'a str) -> Vec<Vec<&'a str>> {
+        vec![line.split_whitespace().collect()]
+    }
+}
+
+impl Tokenizer for Chars {
+    fn sentences<'a>(&self, line: &'a str) -> Vec<Vec<&'a str>> {
+        line.split_whitespace().map(split_chars).collect()
+    }
+}
+
+impl Tokenizer for Punctuation {
+    fn sentences<'a>(&self, line: &'a str) -> Vec<Vec<&'a str>> {
+        vec![line.split_whitespace().flat_map(split_punctuation).collect()]
+    }
+}
+
+impl Separators {
+    fn split<'s, 'a: 's>(&'s self, line: &'a str) -> impl Iterator<Item = &'a str> + 's {
+        line.split(move |c: char| (self.whitespace && c.is_whitespace()) || self.chars.contains(&c))
+            .filter(|word| !word.is_empty())
+    }
+}
+
+impl Tokenizer for Separators {
+    fn sentences<'a>(&self, line: &'a str) -> Vec<Vec<&'a str>> {
+        vec![self.split(line).collect()]
+    }
+}
+
+impl Tokenizer for Matches {
+    fn sentences<'a>(&self, line: &'a str) -> Vec<Vec<&'a str>> {
+        vec![self.0.find_iter(line).map(|word| word.as_str()).collect()]
+    }
+}
+
+impl Tokenizer for Builtin {
+    fn sentences<'a>(&self, line: &'a str) -> Vec<Vec<&'a str>> {
+        let words: Box<dyn Iterator<Item = &'a str> + '_> = match (&self.regex, &self.separators) {
+            (Some(regex), _) => Box::new(regex.find_iter(line).map(|word| word.as_str())),
+            (None, Some(separators)) => Box::new(separators.split(line)),
+            (None, None) => Box::new(line.split_whitespace()),
+        };
+        match self.tokens {
+            Tokens::Words => vec![words.collect()],
+            Tokens::Punct => vec![words.flat_map(split_punctuation).collect()],
+            Tokens::Chars => words.map(split_chars).collect(),
+        }
+    }
+}
+
+fn split_chars(word: &str) -> Vec<&str> {
+    word.char_indices()
+        .map(|(at, c)| &word[at..at + c.len_utf8()])
+        .collect()
+}
+
+// splits "hello," into "hello" and ",", keeping apostrophes and hyphens
+// within words like "don't" and "well-known"
+fn split_punctuation(word: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = word.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, &(at, c)) in chars.iter().enumerate() {
+        let joins = (c == '\'' || c == '-')
+            && i > 0 && chars[i - 1].1.is_alphanumeric()
+            && chars.get(i + 1).is_some_and(|&(_, next)| next.is_alphanumeric());
+        if c.is_alphanumeric() || joins {
+            start.get_or_insert(at);
+            continue;
+        }
+        if let Some(start) = start.take() {
+            tokens.push(&word[start..at]);
+        }
+        tokens.push(&word[at..at + c.len_utf8()]);
+    }
+    if let Some(start) = start {
+        tokens.push(&word[start..]);
+    }
+    tokens
+}
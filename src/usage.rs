@@ -0,0 +1,504 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::sampler::{Sampling, Step};
+use crate::{normalize, Sampler, Stats, END};
+
+// how many times in a row a context may lead back to itself
+const MAX_STUCK: usize = 30;
+
+// how many times a recently used word is drawn again before giving in
+const MAX_RESAMPLES: usize = 5;
+
+// how many words before the end word are steered towards it
+const HORIZON: usize = 12;
+
+// chances too small to steer anything are forgotten along the way
+const NEGLIGIBLE: f64 = 1e-9;
+
+/// Settings used to generate sentences.
+#[derive(Clone, Debug)]
+pub struct GeneratorConfig {
+    /// Share of the most frequent contexts to start from, for models
+    /// without start tokens.
+    pub threshold: f32,
+    /// Reshapes the probabilities of each word, see [`Sampler`].
+    pub temperature: f64,
+    /// Added to the count of every word after every context, so that no
+    /// word is ever impossible.
+    pub smoothing: f64,
+    /// Never draw [`UNK`](crate::UNK).
+    pub skip_unknown: bool,
+    /// Seed for reproducible sentences, random when not given.
+    pub seed: Option<u64>,
+    /// Sentences that end before this many words go on with a new
+    /// sentence from a fresh start context instead.
+    pub min_words: usize,
+    /// Sentences are cut short after this many words.
+    pub max_words: Option<usize>,
+    /// Avoid drawing any of the last this many words of a sentence again.
+    /// This is only a preference: when a recent word keeps being drawn,
+    /// it is let through anyway.
+    pub no_repeat: usize,
+    /// Start every sentence with this word, normalized like the model.
+    /// With an order above 1, some sentence must have started with it
+    /// during training.
+    pub start: Option<String>,
+    /// Sentences that reach a context nothing ever followed before
+    /// `min_words` go on from a fresh start context, instead of ending
+    /// there.
+    pub restart_on_dead_end: bool,
+    /// Chance of ending the sentence after each word, once it has
+    /// `min_words`, so sentences vary in length even in models without
+    /// end tokens. Values outside `0..=1` are taken as 0.
+    pub stop_prob: f64,
+    /// Contexts of higher order models that were never seen, or that
+    /// nothing ever followed, draw their next word from every context
+    /// ending with the same words, the longest ones found, instead of
+    /// ending the sentence.
+    pub backoff: bool,
+    /// Sentences stop with the words they have after this many words
+    /// were drawn for them, counting the ones drawn again and those of
+    /// sentences started over, so no model can keep a sentence going on
+    /// for long.
+    pub max_attempts: Option<usize>,
+    pub sampling: Sampling,
+}
+
+/// Generates an endless stream of words from a trained model.
+///
+/// Each word is drawn by the [`Sampler`], with a probability based on how
+/// often it followed the current context during training, until the end
+/// of the sentence is drawn. A sentence also ends when it gets stuck
+/// looping on the same context, so degenerate models cannot go on
+/// forever.
+///
+/// Models without start tokens start from one of their most frequent
+/// contexts instead, picked in proportion to how often each was seen.
+/// The `threshold` is the share of all transitions these contexts make
+/// up, so lower ones only ever start from the most frequent contexts.
+#[derive(Clone, Debug)]
+pub struct Usage<'a> {
+    current: Option<u32>,
+    stuck: usize,
+    sampler: Cow<'a, Sampler<'a>>,
+    rng: StdRng,
+    // contexts to start from when there are no start tokens
+    starts: Vec<(u32, i32)>,
+    // the context and word every sentence starts with
+    prompt: Option<(u32, u32)>,
+    pending: Option<u32>,
+    min_words: usize,
+    max_words: Option<usize>,
+    emitted: usize,
+    no_repeat: usize,
+    recent: VecDeque<u32>,
+    goal: Option<Goal>,
+    restart_on_dead_end: bool,
+    dead_end: Option<u32>,
+    stop_prob: f64,
+    // the words of the current context, when the model never saw it
+    unseen: Option<String>,
+    max_attempts: Option<usize>,
+    attempts: usize,
+    exhausted: bool,
+}
+
+// the word sentences are steered towards, and how likely each word is
+// to lead to it
+#[derive(Clone, Debug)]
+struct Goal {
+    word: u32,
+    guide: HashMap<u32, f64>,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            threshold: 0.75,
+            temperature: 1.0,
+            smoothing: 0.0,
+            skip_unknown: false,
+            seed: None,
+            min_words: 0,
+            max_words: None,
+            no_repeat: 0,
+            start: None,
+            restart_on_dead_end: false,
+            stop_prob: 0.0,
+            backoff: false,
+            max_attempts: None,
+            sampling: Sampling::Uniform,
+        }
+    }
+}
+
+impl<'a> Usage<'a> {
+    /// Creates a generator, seeding its RNG with `seed` when given.
+    pub fn new(threshold: f32, sampler: &'a Sampler<'a>, seed: Option<u64>) -> Self {
+        let config = GeneratorConfig {
+            threshold,
+            seed,
+            ..GeneratorConfig::default()
+        };
+        Usage::build(Cow::Borrowed(sampler), &config)
+    }
+
+    /// Creates a generator with every setting of `config`, except the
+    /// ones of the [`Sampler`] itself.
+    ///
+    /// Fails when the start word is not found in the model.
+    pub fn with_config(sampler: &'a Sampler<'a>, config: &GeneratorConfig) -> Result<Self, String> {
+        Usage::configured(Cow::Borrowed(sampler), config)
+    }
+
+    // the same as with_config, for a sampler that may be owned
+    pub(crate) fn configured(sampler: Cow<'a, Sampler<'a>>, config: &GeneratorConfig) -> Result<Self, String> {
+        let mut usage = Usage::build(sampler, config);
+        usage.set_start(config.start.as_deref())?;
+        Ok(usage)
+    }
+
+    /// Starts a new sentence with `word`, and every sentence after it,
+    /// or from a fresh start context again when not given.
+    ///
+    /// Fails when the start word is not found in the model, leaving the
+    /// start of sentences as it was.
+    pub fn set_start(&mut self, word: Option<&str>) -> Result<(), String> {
+        self.prompt = match word {
+            Some(word) => {
+                let stats = self.sampler.stats();
+                let normalized = normalize(word, &stats.normalization);
+                let context = match start_context(stats) {
+                    Some(_) => stats.advance(&stats.start(), &normalized),
+                    None => normalized.clone(),
+                };
+                let context = stats.words
+                    .id(&context)
+                    .filter(|context| stats.of.contains_key(context));
+                let prompt = context
+                    .zip(stats.words.id(&normalized))
+                    .ok_or_else(|| format!("{} not found in the model", word))?;
+                Some(prompt)
+            },
+            None => None,
+        };
+        self.restart();
+        Ok(())
+    }
+
+    /// Steers every sentence towards ending with `word`, or lets them end
+    /// wherever again when not given.
+    ///
+    /// The `backward` model is the same as the one being sampled, but
+    /// learned in the other direction. From it, every word is weighed by
+    /// how likely it is to come shortly before `word`, and each word drawn
+    /// is favored in proportion to that. Sentences end as soon as `word`
+    /// is drawn.
+    ///
+    /// Fails when `word` never ends a sentence in the `backward` model,
+    /// leaving the end of sentences as it was.
+    pub fn set_end(&mut self, backward: &Stats, word: Option<&str>) -> Result<(), String> {
+        self.goal = match word {
+            Some(word) => Some(goal(self.sampler.stats(), backward, word)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// The last context the current sentence reached that nothing ever
+    /// followed, if any.
+    pub fn dead_end(&self) -> Option<&'a str> {
+        self.dead_end.map(|context| self.sampler.stats().words.get(context))
+    }
+
+    /// Whether the current sentence stopped because it ran out of
+    /// [`max_attempts`](GeneratorConfig::max_attempts).
+    pub fn exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    fn build(sampler: Cow<'a, Sampler<'a>>, config: &GeneratorConfig) -> Self {
+        let threshold = if !(0.0..=1.0).contains(&config.threshold) {
+            0.75
+        } else {
+            config.threshold
+        };
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let stats = sampler.stats();
+        let starts = match start_context(stats) {
+            Some(_) => Vec::new(),
+            None => frequent_contexts(stats, threshold),
+        };
+        let mut usage = Usage {
+            sampler,
+            rng,
+            current: None,
+            stuck: 0,
+            starts,
+            prompt: None,
+            pending: None,
+            min_words: config.min_words,
+            max_words: config.max_words,
+            emitted: 0,
+            no_repeat: config.no_repeat,
+            recent: VecDeque::new(),
+            goal: None,
+            restart_on_dead_end: config.restart_on_dead_end,
+            dead_end: None,
+            stop_prob: match config.stop_prob {
+                p if (0.0..=1.0).contains(&p) => p,
+                _ => 0.0,
+            },
+            unseen: None,
+            max_attempts: config.max_attempts,
+            attempts: 0,
+            exhausted: false,
+        };
+        usage.restart();
+        usage
+    }
+
+    /// Starts a new sentence from a fresh start context.
+    pub fn restart(&mut self) {
+        self.emitted = 0;
+        self.attempts = 0;
+        self.exhausted = false;
+        self.dead_end = None;
+        self.recent.clear();
+        if let Some((context, word)) = self.prompt {
+            self.stuck = 0;
+            self.unseen = None;
+            self.current = Some(context);
+            self.pending = Some(word);
+            return;
+        }
+        self.begin();
+    }
+
+    // moves to a fresh start context, without counting a new sentence
+    fn begin(&mut self) {
+        self.stuck = 0;
+        self.unseen = None;
+        let stats = self.sampler.stats();
+        if let Some(start) = start_context(stats) {
+            self.current = Some(start);
+            return;
+        }
+
+        let total: i32 = self.starts.iter().map(|&(_, number)| number).sum();
+        if total == 0 {
+            self.current = None;
+            return;
+        }
+        let mut pick = self.rng.gen_range(0, total);
+        for &(context, number) in &self.starts {
+            if pick < number {
+                self.current = Some(context);
+                return;
+            }
+            pick -= number;
+        }
+    }
+
+    fn emit(&mut self, word: u32) -> &'a str {
+        self.emitted += 1;
+        if self.no_repeat > 0 {
+            if self.recent.len() == self.no_repeat {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(word);
+        }
+        self.sampler.stats().words.get(word)
+    }
+
+    // draws the next word, unless the sentence ends
+    fn step(&mut self) -> Option<u32> {
+        let current = match self.current {
+            Some(current) => current,
+            None => return self.step_back(),
+        };
+        let words = &self.sampler.stats().words;
+        let mut step = match self.draw(current) {
+            Some(step) => step,
+            None if self.sampler.backs_off() => {
+                self.current = None;
+                self.unseen = Some(words.get(current).to_string());
+                return self.step_back();
+            },
+            None => {
+                self.dead_end = Some(current);
+                return None;
+            },
+        };
+        for _ in 0..MAX_RESAMPLES {
+            if !self.recent.contains(&step.word) {
+                break;
+            }
+            step = self.draw(current)?;
+        }
+        if Some(step.word) == self.sampler.end {
+            return None;
+        }
+
+        if step.context == Some(current) {
+            self.stuck += 1;
+            if self.stuck > MAX_STUCK {
+                return None;
+            }
+        } else {
+            self.stuck = 0;
+        }
+        self.current = step.context;
+        if self.goal.as_ref().is_some_and(|goal| goal.word == step.word) {
+            self.current = None;
+        } else if step.context.is_none() && self.sampler.backs_off() {
+            let stats = self.sampler.stats();
+            self.unseen = Some(stats.advance(words.get(current), words.get(step.word)));
+        } else if step.context.is_none() {
+            self.dead_end = Some(step.word);
+        }
+        Some(step.word)
+    }
+
+    // draws the next word of a context the model never saw, until one it
+    // did is reached again
+    fn step_back(&mut self) -> Option<u32> {
+        let context = self.unseen.take()?;
+        self.attempts += 1;
+        let step = self.sampler.back_off(&context, &mut self.rng)?;
+        if Some(step.word) == self.sampler.end {
+            return None;
+        }
+        self.current = step.context;
+        let stats = self.sampler.stats();
+        if self.goal.as_ref().is_some_and(|goal| goal.word == step.word) {
+            self.current = None;
+        } else if step.context.is_none() {
+            self.unseen = Some(stats.advance(&context, stats.words.get(step.word)));
+        }
+        Some(step.word)
+    }
+
+    fn draw(&mut self, current: u32) -> Option<Step> {
+        self.attempts += 1;
+        match &self.goal {
+            Some(goal) => self.sampler.steer(current, &goal.guide, &mut self.rng),
+            None => self.sampler.step(current, &mut self.rng),
+        }
+    }
+}
+
+impl<'a> Iterator for Usage<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.max_words.is_some_and(|max_words| self.emitted >= max_words) {
+            return None;
+        }
+        if let Some(word) = self.pending.take() {
+            return Some(self.emit(word));
+        }
+        // the rng is left alone otherwise, so seeded sentences stay the same
+        if self.stop_prob > 0.0 && self.emitted > 0 && self.emitted >= self.min_words
+            && self.rng.gen_bool(self.stop_prob) {
+            return None;
+        }
+
+        let mut restarted = false;
+        loop {
+            if self.max_attempts.is_some_and(|max_attempts| self.attempts >= max_attempts) {
+                self.exhausted = true;
+                return None;
+            }
+            match self.step() {
+                Some(word) => return Some(self.emit(word)),
+                None if self.dead_end.is_some() && !self.restart_on_dead_end => return None,
+                // models that cannot even start would loop forever, so a
+                // fresh start has to draw something
+                None if self.emitted < self.min_words && !restarted => {
+                    self.begin();
+                    self.current?;
+                    restarted = true;
+                },
+                None => return None,
+            }
+        }
+    }
+}
+
+// walks `backward` from the end of sentences ending with `word`, adding
+// up how likely each word is to be seen on the way
+fn goal(stats: &Stats, backward: &Stats, word: &str) -> Result<Goal, String> {
+    let normalized = normalize(word, &backward.normalization);
+    let end = backward.advance(&backward.start(), &normalized);
+    let goal = stats.words
+        .id(&normalized)
+        .filter(|_| backward.get(&end).is_some())
+        .ok_or_else(|| format!("{} never ends a sentence in the model", word))?;
+
+    let mut guide = HashMap::new();
+    guide.insert(goal, 1.0);
+    let mut reached: HashMap<String, f64> = HashMap::new();
+    reached.insert(end, 1.0);
+    for _ in 0..HORIZON {
+        let mut next: HashMap<String, f64> = HashMap::new();
+        for (context, chance) in &reached {
+            let stat = match backward.get(context) {
+                Some(stat) => stat,
+                None => continue,
+            };
+            let total = f64::from(stat.total());
+            for (neigh, number) in backward.next(context).into_iter().flatten() {
+                let chance = chance * f64::from(number) / total;
+                if neigh == END || chance < NEGLIGIBLE {
+                    continue;
+                }
+                if let Some(neigh) = stats.words.id(neigh) {
+                    *guide.entry(neigh).or_insert(0.0) += chance;
+                }
+                *next.entry(backward.advance(context, neigh)).or_insert(0.0) += chance;
+            }
+        }
+        reached = next;
+    }
+    Ok(Goal { word: goal, guide })
+}
+
+fn start_context(stats: &Stats) -> Option<u32> {
+    stats.words
+        .id(&stats.start())
+        .filter(|start| stats.of.contains_key(start))
+}
+
+// the most frequent contexts making up a `threshold` share of all
+// transitions, along with how often each was seen
+fn frequent_contexts(stats: &Stats, threshold: f32) -> Vec<(u32, i32)> {
+    let mut contexts: Vec<(u32, i32)> = stats.of
+        .iter()
+        .map(|(&context, stat)| (context, stat.total()))
+        .collect();
+    contexts.sort_by(|&(a, x), &(b, y)| {
+        y.cmp(&x).then_with(|| stats.words.get(a).cmp(stats.words.get(b)))
+    });
+
+    let total: i64 = contexts.iter().map(|&(_, number)| i64::from(number)).sum();
+    let wanted = (total as f64 * f64::from(threshold)).ceil() as i64;
+    let mut seen = 0;
+    let mut keep = 0;
+    for &(_, number) in &contexts {
+        keep += 1;
+        seen += i64::from(number);
+        if seen >= wanted {
+            break;
+        }
+    }
+    contexts.truncate(keep);
+    contexts
+}
@@ -0,0 +1,37 @@
+//! Bindings to train and generate from JavaScript, see `examples/wasm`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{GeneratorConfig, Stats, TrainConfig};
+
+/// A model trained in the browser.
+#[wasm_bindgen]
+pub struct Model {
+    stats: Stats,
+}
+
+/// Trains a model with the default settings on every line of `text`.
+#[wasm_bindgen]
+pub fn train(text: &str) -> Model {
+    Model {
+        stats: Stats::train(text.lines(), &TrainConfig::default()),
+    }
+}
+
+#[wasm_bindgen]
+impl Model {
+    /// Makes up a sentence of at most `words` words, the same one for the
+    /// same `seed`.
+    pub fn generate(&self, words: usize, seed: u32) -> String {
+        let config = GeneratorConfig {
+            seed: Some(u64::from(seed)),
+            max_words: Some(words),
+            ..GeneratorConfig::default()
+        };
+        // without a start word, generating cannot fail
+        self.stats
+            .generate(&config)
+            .map(|words| words.collect::<Vec<_>>().join(" "))
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,690 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+fn papagaio(args: &[&str], input: &str) -> Output {
+    papagaio_with_env(args, &[], input)
+}
+
+fn papagaio_with_env(args: &[&str], vars: &[(&str, &str)], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_papagaio"))
+        .args(args)
+        .envs(vars.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // invalid arguments exit before reading stdin, closing it early
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+// a file under the temporary directory for the flags that take a path
+fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("papagaio-{}-{}", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn empty_input() {
+    let output = papagaio(&[], "");
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "error: no training data\n");
+}
+
+#[test]
+fn invalid_threshold() {
+    let output = papagaio(&["-t", "foo"], "a b");
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: invalid value for -t 'foo': "));
+}
+
+#[test]
+fn invalid_words() {
+    let output = papagaio(&["-w", "bar"], "a b");
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: invalid value for -w 'bar': "));
+}
+
+#[test]
+fn threshold_without_value() {
+    let output = papagaio(&["-t"], "a b");
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: -t requires a value\n"));
+}
+
+#[test]
+fn words_without_value() {
+    let output = papagaio(&["-n", "2", "-w"], "a b");
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: -w requires a value\n"));
+}
+
+#[test]
+fn version() {
+    let output = papagaio(&["-V"], "");
+
+    assert!(output.status.success());
+    let expected = format!("papagaio {}\n", env!("CARGO_PKG_VERSION"));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn closed_stdout() {
+    for arg in ["-h", "-V"] {
+        let (reader, writer) = std::io::pipe().unwrap();
+        drop(reader);
+        let status = Command::new(env!("CARGO_BIN_EXE_papagaio"))
+            .arg(arg)
+            .stdout(writer)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{}", arg);
+    }
+}
+
+#[test]
+fn help_is_aligned() {
+    let output = papagaio(&["-h"], "");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let options = stdout.lines().skip_while(|line| *line != "options:").skip(1);
+
+    // flags too wide for the column get a line of their own
+    for line in options.take_while(|line| !line.is_empty()) {
+        let alone = !line[2..].contains("  ");
+        let aligned = line.len() > 31 && line[..31].ends_with(' ') && !line[31..].starts_with(' ');
+        assert!(alone || aligned, "{}", line);
+    }
+    assert!(stdout.contains("  --number-token <keep|collapse|drop>\n                               learn numbers"), "{}", stdout);
+}
+
+#[test]
+fn cycles() {
+    let output = papagaio(&["--cycles", "3"], "a b a b c\nb a c\nc c\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b\t0.444444\nc\t0.250000\n");
+}
+
+#[test]
+fn greedy() {
+    let output = papagaio(&["--greedy"], "a b a c\nb a b a\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b a\n");
+}
+
+#[test]
+fn greedy_output() {
+    let output = papagaio(&["--reverse", "--greedy"], "a b c\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b c\n");
+    let output = papagaio(&["--greedy", "--prose", "-n", "2"], "a b c\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "A b c.\nA b c.\n");
+    let output = papagaio(&["--greedy", "--paragraph", "2", "--wrap", "7"], "a b c\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b c a\nb c\n");
+}
+
+#[test]
+fn beam() {
+    let input = "a b\na b\na c d\n";
+
+    let output = papagaio(&["--beam", "2"], input);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b\n");
+    let output = papagaio(&["--beam", "2", "--min-words", "3"], input);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a c d\n");
+
+    // "a x" is a dead end once what follows x is pruned
+    let input = "a x b\na x c\nd e f\nd e f\n";
+    let output = papagaio(&["--beam", "2", "--min-count", "2"], input);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a x\n");
+    let output = papagaio(&["--beam", "2", "--min-count", "2", "--min-words", "3"], input);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "d e f\n");
+}
+
+#[test]
+fn beam_output() {
+    let output = papagaio(&["--reverse", "--beam", "2"], "a b c\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b c\n");
+    let output = papagaio(&["--beam", "2", "--prose", "-n", "2", "--wrap", "4"], "a b c\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "A b\nc.\nA b\nc.\n");
+}
+
+#[test]
+fn paragraphs_wrap_as_one() {
+    let output = papagaio(&["--paragraph", "3", "--wrap", "7"], "a b c\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b c a\nb c a b\nc\n");
+}
+
+#[test]
+fn prose_closes_quotes() {
+    let output = papagaio(&["--tokenizer", "punct", "--order", "3", "--prose"], "she said ' hi ' and \" bye \" .\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "She said 'hi' and \"bye\".\n");
+}
+
+#[test]
+fn flags_override_the_environment() {
+    let output = papagaio_with_env(&[], &[("PAPAGAIO_WORDS", "1")], "a b c");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\n");
+    let output = papagaio_with_env(&["-w", "2"], &[("PAPAGAIO_WORDS", "1")], "a b c");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b\n");
+}
+
+#[test]
+fn invalid_environment() {
+    let output = papagaio_with_env(&[], &[("PAPAGAIO_SEED", "foo")], "a b");
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: invalid value for PAPAGAIO_SEED 'foo': "));
+
+    let output = papagaio_with_env(&[], &[("PAPAGAIO_WORDS", "0")], "a b");
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "error: invalid value for PAPAGAIO_WORDS '0': -w must be at least 1\n");
+}
+
+#[test]
+fn summary() {
+    let output = papagaio(&["--summary"], "a b a\nb a c\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("tokens: 6\ndistinct words: 3\ndistinct transitions: 7\ntop words:\n  a\t3\n"), "{}", stdout);
+    assert!(stdout.contains("top transitions:\n  b -> a\t2\n"), "{}", stdout);
+}
+
+#[test]
+fn print_prefix() {
+    let output = papagaio(&["--print-prefix", "B"], "a b\nbe c\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"b\"") && stdout.contains("\"be\""), "{}", stdout);
+    assert!(!stdout.contains("\"a\"") && !stdout.contains("\"c\":"), "{}", stdout);
+}
+
+#[test]
+fn print_reads_files_after_it() {
+    let path = temp_file("print.txt", b"a b\n");
+    let output = papagaio(&["-p", path.to_str().unwrap()], "");
+    fs::remove_file(path).unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"a\"") && stdout.contains("\"b\""), "{}", stdout);
+}
+
+#[test]
+fn seeded_runs_are_identical() {
+    let input = "the cat sat on the mat\nthe dog sat on the log\na cat and a dog\non the mat the cat sat\n";
+
+    for args in [&["-s", "7", "-n", "20"][..], &["-s", "7", "-n", "20", "-t", "0.5", "--order", "2"]] {
+        let first = papagaio(args, input);
+        let second = papagaio(args, input);
+        assert!(first.status.success());
+        assert!(!first.stdout.is_empty());
+        assert_eq!(first.stdout, second.stdout);
+    }
+}
+
+#[test]
+fn values_after_equals_signs() {
+    for args in [&["-w", "1"][..], &["-w=1"], &["--words", "1"], &["--words=1"]] {
+        let output = papagaio(args, "a b c");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a\n", "{:?}", args);
+    }
+    for args in [&["-t", "foo"][..], &["-t=foo"], &["--threshold", "foo"], &["--threshold=foo"]] {
+        let output = papagaio(args, "a b");
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.starts_with(&format!("error: invalid value for {} 'foo': ", args[0].split('=').next().unwrap())), "{}", stderr);
+    }
+}
+
+#[test]
+fn combined_short_flags() {
+    for args in [&["-w1"][..], &["-vw1"], &["-vw", "1"], &["-vw=1"], &["-s7", "-w1"]] {
+        let output = papagaio(args, "a b c");
+        assert!(output.status.success(), "{:?}", args);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a\n", "{:?}", args);
+    }
+    let output = papagaio(&["-t0.x"], "a b");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: invalid value for -t '0.x': "), "{}", stderr);
+    let output = papagaio(&["-vx"], "a b");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: invalid flag: -vx\n"), "{}", stderr);
+}
+
+#[test]
+fn values_are_not_taken_apart() {
+    for delimiter in ["-=-", "-vs"] {
+        let output = papagaio(&["--delimiter", delimiter], "a b");
+        assert!(output.status.success(), "{}", delimiter);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), format!("a{}b\n", delimiter));
+    }
+}
+
+#[test]
+fn meta_reproduces_runs() {
+    let input = "the cat sat\na dog ran\nthe dog sat\n";
+    let output = papagaio(&["--show-meta", "-n", "3"], input);
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("threshold: 0.75\nwords: 100\nstart: "), "{}", stderr);
+    let seed = stderr.lines().find_map(|line| line.strip_prefix("seed: ")).unwrap();
+    let again = papagaio(&["-s", seed, "-n", "3"], input);
+    assert_eq!(output.stdout, again.stdout);
+}
+
+#[test]
+fn dot() {
+    let output = papagaio(&["--format", "dot"], "a b\n");
+
+    assert!(output.status.success());
+    let expected = "digraph papagaio {\n    \"<START>\" -> \"a\" [label=1];\n    \"a\" -> \"b\" [label=1];\n    \"b\" -> \"<END>\" [label=1];\n}\n";
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn json() {
+    let output = papagaio(&["--format", "json"], "a b\na c\n");
+
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["of"]["a"]["next"], serde_json::json!({ "b": 1, "c": 1 }));
+    assert_eq!(json["of"]["<START>"]["next"], serde_json::json!({ "a": 2 }));
+}
+
+#[test]
+fn min_count() {
+    let output = papagaio(&["--min-count", "2", "--query", "a"], "a b\na b\na c\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "b\t2\t100.00%\n");
+}
+
+#[test]
+fn vocab() {
+    let output = papagaio(&["--vocab", "1", "--format", "dot"], "a b a\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"a\" -> \"<UNK>\" [label=1];\n"), "{}", stdout);
+    assert!(stdout.contains("\"<UNK>\" -> \"a\" [label=1];\n"), "{}", stdout);
+    assert!(!stdout.contains("\"b\""), "{}", stdout);
+}
+
+#[test]
+fn vocab_keeps_only_its_forms() {
+    let output = papagaio(&["--vocab", "1", "--format", "json"], "Apple Banana Apple Cherry\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let forms = &stdout[stdout.find("\"forms\"").unwrap()..];
+    assert!(forms.contains("\"Apple\": 2"), "{}", forms);
+    assert!(!forms.contains("anana") && !forms.contains("herry"), "{}", forms);
+}
+
+#[test]
+fn query() {
+    let output = papagaio(&["--query", "a"], "a b\na c\na b\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "b\t2\t66.67%\nc\t1\t33.33%\n");
+    let output = papagaio(&["--query", "d"], "a b\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "error: d not found in the model\n");
+}
+
+#[test]
+fn score() {
+    let path = temp_file("score.txt", b"a b\n");
+    let output = papagaio(&["--score", path.to_str().unwrap()], "a b\na c\n");
+    fs::remove_file(path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "log-probability: -2.7081\nperplexity: 2.4662\n");
+    let output = papagaio(&["--score", "-"], "a b\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "error: cannot both train on and score stdin\n");
+}
+
+#[test]
+fn smoothing() {
+    let path = temp_file("smoothing.txt", b"a b\n");
+    let score = |smoothing| {
+        let output = papagaio(&["--score", path.to_str().unwrap(), "--smoothing", smoothing], "a b\na c\n");
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // without smoothing only b following a is left to chance
+    assert_eq!(score("0"), "log-probability: -0.6931\nperplexity: 1.2599\n");
+    assert_eq!(score("2"), "log-probability: -3.2189\nperplexity: 2.9240\n");
+    fs::remove_file(path).unwrap();
+
+    for smoothing in ["NaN", "inf", "-1"] {
+        let output = papagaio(&["--smoothing", smoothing], "a b\n");
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let expected = format!("error: smoothing must be a finite number of at least 0, not {}\n", smoothing);
+        assert!(stderr.starts_with(&expected), "{}", stderr);
+    }
+}
+
+#[test]
+fn no_repeat() {
+    let input = "a b a c\na b d\n";
+
+    let output = papagaio(&["-s", "1", "-n", "20"], input);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("a b a"));
+    // after "a b", d is drawn rather than a again
+    let output = papagaio(&["-s", "1", "-n", "20", "--no-repeat", "2"], input);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().all(|line| line == "a b d" || line == "a c"), "{}", stdout);
+}
+
+#[test]
+fn delimiter() {
+    let output = papagaio(&["--delimiter", "\\t"], "a b c\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\tb\tc\n");
+    let output = papagaio(&["--no-newline", "-n", "2"], "a b c\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b c\na b c");
+}
+
+#[test]
+fn wrap() {
+    let output = papagaio(&["--wrap", "3"], "a b c d\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b\nc d\n");
+    // words wider than the line go on one of their own
+    let output = papagaio(&["--wrap", "2"], "a bcd e\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\nbcd\ne\n");
+}
+
+#[test]
+fn gzip() {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"a b c\n").unwrap();
+    let path = temp_file("corpus.gz", &encoder.finish().unwrap());
+    let output = papagaio(&[path.to_str().unwrap()], "");
+    fs::remove_file(path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b c\n");
+}
+
+#[test]
+fn byte_order_marks() {
+    let first = temp_file("first-bom.txt", "\u{feff}a b\n".as_bytes());
+    let second = temp_file("second-bom.txt", "\u{feff}c d\n".as_bytes());
+    let output = papagaio(&["--format", "dot", first.to_str().unwrap(), second.to_str().unwrap()], "");
+    fs::remove_file(first).unwrap();
+    fs::remove_file(second).unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"<START>\" -> \"a\" [label=1];\n"), "{}", stdout);
+    assert!(stdout.contains("\"<START>\" -> \"c\" [label=1];\n"), "{}", stdout);
+    assert!(!stdout.contains('\u{feff}'), "{}", stdout);
+}
+
+#[test]
+fn output_file() {
+    let path = std::env::temp_dir().join(format!("papagaio-{}-output.txt", std::process::id()));
+    let output = papagaio(&["-o", path.to_str().unwrap()], "a b c\n");
+    let written = fs::read_to_string(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(written, "a b c\n");
+}
+
+#[test]
+fn top() {
+    let output = papagaio(&["--top", "a:1"], "a b\na c\na b\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "b\t2\n");
+    let output = papagaio(&["--top", "a"], "a b\n");
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: invalid top: a, expected <word>:<n>\n"));
+}
+
+#[test]
+fn interactive() {
+    let path = temp_file("interactive.txt", b"a b\nc d\n");
+    let output = papagaio(&["--interactive", path.to_str().unwrap()], "a\nzz\nc\n");
+    fs::remove_file(path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b\nc d\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "error: zz not found in the model\n");
+}
+
+#[test]
+fn bidirectional() {
+    let input = "a b c\nx b y\n";
+
+    let output = papagaio(&["-s", "3", "-n", "6"], input);
+    assert!(String::from_utf8_lossy(&output.stdout).lines().any(|line| line.ends_with('c')));
+    let output = papagaio(&["-s", "3", "-n", "6", "--bidirectional", "--end", "y"], input);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().all(|line| line.ends_with(" b y")), "{}", stdout);
+    let output = papagaio(&["--end", "y"], input);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "error: --bidirectional and --end must be given together\n");
+}
+
+#[test]
+fn no_self_loops() {
+    let output = papagaio(&["--no-self-loops", "--query", "a"], "a a a b\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "b\t1\t100.00%\n");
+}
+
+#[test]
+fn stopwords() {
+    let path = temp_file("stopwords.txt", b"The\n");
+    let output = papagaio(&["--stopwords", path.to_str().unwrap()], "a the b\n");
+    fs::remove_file(path).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b\n");
+}
+
+#[test]
+fn min_token_len() {
+    let output = papagaio(&["--min-token-len", "2"], "a bb c dd\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "bb dd\n");
+}
+
+#[test]
+fn lossy() {
+    let path = temp_file("lossy.txt", b"a \xff b\n");
+    let strict = papagaio(&[path.to_str().unwrap()], "");
+    let lossy = papagaio(&["--lossy", path.to_str().unwrap()], "");
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(strict.status.code(), Some(1));
+    let stderr = String::from_utf8(strict.stderr).unwrap();
+    assert!(stderr.starts_with("error: line 1 is not valid UTF-8"), "{}", stderr);
+    assert_eq!(String::from_utf8_lossy(&lossy.stdout), "a \u{fffd} b\n");
+}
+
+#[test]
+fn split_on() {
+    let output = papagaio(&["--split-on", ","], "a,b c\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b c\n");
+    let output = papagaio(&["--split-only-on", ",", "--format", "dot"], "a b,c\n");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"<START>\" -> \"a\u{a0}b\" [label=1];\n"), "{}", stdout);
+}
+
+#[test]
+fn spaces_within_tokens() {
+    let output = papagaio(&["--split-only-on", ",", "--order", "2"], "a b,c,d\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b c d\n");
+    let output = papagaio(&["--split-only-on", ",", "--order", "2", "--format", "dot"], "a b,c,d\n");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"<START> a\u{a0}b\" -> \"a\u{a0}b c\" [label=1];\n"), "{}", stdout);
+}
+
+#[test]
+fn temperature() {
+    let output = papagaio(&["--temperature", "0.01", "-s", "2", "-n", "10"], "a b\na b\na b\na c\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b\n".repeat(10));
+}
+
+#[test]
+fn char() {
+    let output = papagaio(&["--char", "--format", "dot"], "ab\n");
+
+    assert!(output.status.success());
+    let expected = "digraph papagaio {\n    \"<START>\" -> \"a\" [label=1];\n    \"a\" -> \"b\" [label=1];\n    \"b\" -> \"<END>\" [label=1];\n}\n";
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn normalization() {
+    let output = papagaio(&["--format", "dot"], "\u{fb01}ne\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"fine\""));
+    let output = papagaio(&["--normalization", "nfc", "--format", "dot"], "\u{fb01}ne\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"\u{fb01}ne\""));
+}
+
+#[test]
+fn reverse() {
+    let output = papagaio(&["--reverse", "--query", "c"], "a b c\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "b\t1\t100.00%\n");
+    // sentences are learned backwards, but come out the right way
+    let output = papagaio(&["--reverse"], "a b c\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b c\n");
+}
+
+#[test]
+fn preserve_case() {
+    let output = papagaio(&["--format", "dot"], "Paris big\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"paris\" -> \"big\""));
+    for flag in ["--preserve-case", "--no-lowercase"] {
+        let output = papagaio(&[flag, "--format", "dot"], "Paris big\n");
+        assert!(String::from_utf8_lossy(&output.stdout).contains("\"Paris\" -> \"big\""), "{}", flag);
+    }
+}
+
+#[test]
+fn weighted_corpora() {
+    let heavy = temp_file("heavy.txt", b"a b\n");
+    let light = temp_file("light.txt", b"a c\n");
+    let heavy_corpus = format!("{}:3", heavy.display());
+    let output = papagaio(&["--corpus", &heavy_corpus, "--corpus", light.to_str().unwrap(), "--query", "a"], "");
+    fs::remove_file(heavy).unwrap();
+    fs::remove_file(light).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "b\t3\t75.00%\nc\t1\t25.00%\n");
+}
+
+#[test]
+fn progress() {
+    let output = papagaio(&["--progress"], "a b\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "read 1 lines, 4 bytes\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b\n");
+}
+
+#[test]
+fn restart_on_dead_end() {
+    // "a x" is a dead end once what follows x is pruned
+    let input = "a x b\na x c\nd e f\nd e f\n";
+
+    let output = papagaio(&["--min-count", "2", "--min-words", "3", "-s", "1", "-n", "10"], input);
+    assert!(String::from_utf8_lossy(&output.stdout).lines().any(|line| line == "a x"));
+    let output = papagaio(&["--min-count", "2", "--min-words", "3", "-s", "1", "-n", "10", "--restart-on-deadend"], input);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().all(|line| line.split(' ').count() >= 3), "{}", stdout);
+}
+
+#[test]
+fn restart_gives_up_without_a_start() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_papagaio"))
+        .args(["--vocab", "0", "--skip-unknown", "--restart-on-deadend", "--min-words", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"a b\n").unwrap();
+
+    let started = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if started.elapsed() > Duration::from_secs(10) {
+            child.kill().unwrap();
+            panic!("still restarting after 10 seconds");
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn load_does_not_train() {
+    let model = std::env::temp_dir().join(format!("papagaio-{}-load.json", std::process::id()));
+    let corpus = temp_file("load-corpus.txt", b"c d\n");
+    let saved = papagaio(&["--save", model.to_str().unwrap()], "a b\n");
+    let output = papagaio(&["--load", model.to_str().unwrap(), corpus.to_str().unwrap()], "");
+    let weighted = papagaio(&["--load", model.to_str().unwrap(), "--corpus", corpus.to_str().unwrap()], "");
+    fs::remove_file(model).unwrap();
+    fs::remove_file(corpus).unwrap();
+
+    assert!(saved.status.success());
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: --load cannot be combined with files or --corpus to train on\n",
+    );
+    assert_eq!(weighted.status.code(), Some(1));
+}
+
+#[test]
+fn light_weights() {
+    let model = std::env::temp_dir().join(format!("papagaio-{}-light.json", std::process::id()));
+    let corpus = temp_file("light-corpus.txt", b"c d\n");
+    papagaio(&["--save", model.to_str().unwrap()], "a b\n");
+    let merged = papagaio(&["--merge", &format!("{}:0.3", model.to_str().unwrap())], "");
+    let rounded = papagaio(&["--merge", &format!("{}:0.5", model.to_str().unwrap())], "");
+    let trained = papagaio(&["--corpus", &format!("{}:0.3", corpus.to_str().unwrap())], "");
+    fs::remove_file(&model).unwrap();
+    fs::remove_file(&corpus).unwrap();
+
+    assert_eq!(merged.status.code(), Some(1));
+    let expected = format!("error: weight of {} must be a number that rounds to at least 1\n", model.display());
+    assert_eq!(String::from_utf8_lossy(&merged.stderr), expected);
+    assert_eq!(String::from_utf8_lossy(&rounded.stdout), "a b\n");
+    assert_eq!(trained.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&trained.stderr);
+    assert!(stderr.starts_with(&format!("error: weight of {} must be a whole number\n", corpus.display())), "{}", stderr);
+}
@@ -0,0 +1,26 @@
+use papagaio::{format, Stats, TrainConfig};
+
+#[test]
+fn csv_round_trips() {
+    let stats = Stats::train(vec!["a, b \"c\"", "a d"], &TrainConfig::default());
+    let mut csv = Vec::new();
+    format::csv(&mut csv, &stats).unwrap();
+
+    assert_eq!(format::read_csv(&csv[..], &TrainConfig::default()).unwrap(), stats);
+}
+
+#[test]
+fn short_rows_tell_their_line() {
+    let csv = "from,to,count\na,b,1\na,c\n";
+    let e = format::read_csv(csv.as_bytes(), &TrainConfig::default()).unwrap_err();
+
+    assert_eq!(e.to_string(), "line 3: expected 3 fields, found 2");
+}
+
+#[test]
+fn invalid_counts_tell_their_line() {
+    let csv = "a,b,1\n\na,c,many\n";
+    let e = format::read_csv(csv.as_bytes(), &TrainConfig::default()).unwrap_err();
+
+    assert!(e.to_string().starts_with("line 3: invalid count 'many': "), "{}", e);
+}
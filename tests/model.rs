@@ -0,0 +1,115 @@
+use papagaio::model::{self, ModelFormat};
+use papagaio::{Stats, TrainConfig};
+
+#[test]
+fn merged_model_round_trips() {
+    let mut stats = Stats::train(vec!["a b c", "a c"], &TrainConfig::default());
+    let mut other = Stats::train(vec!["a b d", "b c"], &TrainConfig::default());
+    other.scale(2.0);
+    stats.merge(other);
+
+    let mut saved = Vec::new();
+    model::save(&mut saved, &stats).unwrap();
+    let loaded = model::load(&saved[..]).unwrap();
+
+    assert_eq!(stats.count("a", "b"), 3);
+    assert_eq!(stats.count("b", "c"), 3);
+    assert_eq!(loaded.contexts().count(), stats.contexts().count());
+    for (context, next) in stats.contexts() {
+        for (neigh, number) in next {
+            assert_eq!(loaded.count(context, neigh), number);
+        }
+    }
+}
+
+#[test]
+fn json_round_trips() {
+    let config = TrainConfig {
+        order: 2,
+        ..TrainConfig::default()
+    };
+    let stats = Stats::train(vec!["the cat sat on the mat", "the dog sat"], &config);
+
+    let json = serde_json::to_string(&stats).unwrap();
+    let loaded: Stats = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(loaded, stats);
+}
+
+#[test]
+fn bincode_is_smaller_than_json() {
+    let lines: Vec<String> = (0..500)
+        .map(|i| format!("sentence number {} goes on and on about {}", i, i % 7))
+        .collect();
+    let stats = Stats::train(&lines, &TrainConfig::default());
+
+    let mut json = Vec::new();
+    model::save_as(&mut json, &stats, ModelFormat::Json).unwrap();
+    let mut binary = Vec::new();
+    model::save_as(&mut binary, &stats, ModelFormat::Bincode).unwrap();
+
+    assert!(binary.len() < json.len(), "{} >= {}", binary.len(), json.len());
+    assert_eq!(model::load(&binary[..]).unwrap(), stats);
+    assert_eq!(model::load(&json[..]).unwrap(), stats);
+}
+
+#[test]
+fn other_versions_fail_to_load() {
+    let json = r#"{"version": 3, "order": 1, "of": {}}"#;
+    let e = model::load_unverified(json.as_bytes()).unwrap_err();
+    assert!(e.to_string().contains("version 3"), "{}", e);
+}
+
+#[test]
+fn corrupted_models_fail_to_load() {
+    let stats = Stats::train(vec!["a b c"], &TrainConfig::default());
+    let mut saved = Vec::new();
+    model::save(&mut saved, &stats).unwrap();
+
+    let corrupted = String::from_utf8(saved).unwrap().replacen("\"b\"", "\"x\"", 1);
+    assert!(model::load(corrupted.as_bytes()).is_err());
+    assert!(model::load_unverified(corrupted.as_bytes()).is_ok());
+}
+
+#[test]
+fn surface_forms_round_trip() {
+    let stats = Stats::train(vec!["Paris is big", "Paris is old"], &TrainConfig::default());
+    let mut saved = Vec::new();
+    model::save(&mut saved, &stats).unwrap();
+
+    let loaded = model::load(&saved[..]).unwrap();
+    assert_eq!(loaded.surface("paris"), "Paris");
+}
+
+#[test]
+fn only_words_spelled_otherwise_keep_forms() {
+    let mut stats = Stats::train(vec!["Paris is big", "paris"], &TrainConfig::default());
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(json["forms"], serde_json::json!({ "paris": { "Paris": 1, "paris": 1 } }));
+
+    stats.merge(Stats::train(vec!["paris is old"], &TrainConfig::default()));
+    assert_eq!(stats.surface("paris"), "paris");
+    assert_eq!(stats.surface("is"), "is");
+}
+
+#[test]
+fn version_1_binary_models_load() {
+    let stats = Stats::train(vec!["a b"], &TrainConfig::default());
+    let mut binary = Vec::new();
+    model::save_as(&mut binary, &stats, ModelFormat::Bincode).unwrap();
+
+    // the same model without forms, which are last and empty, and with
+    // the version right after the magic bytes
+    let mut v1 = binary[..binary.len() - 11].to_vec();
+    assert_eq!(v1[b"papagaio".len()], 2);
+    v1[b"papagaio".len()] = 1;
+    assert_eq!(model::load_unverified(&v1[..]).unwrap(), stats);
+}
+
+#[test]
+fn version_1_models_load() {
+    let json = r#"{"version": 1, "order": 1, "of": {"a": {"next": {"<END>": 1}}}}"#;
+    let stats = model::load_unverified(json.as_bytes()).unwrap();
+    assert_eq!(stats.count("a", "<END>"), 1);
+    assert_eq!(stats.surface("a"), "a");
+}
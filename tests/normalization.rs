@@ -0,0 +1,10 @@
+use papagaio::{normalize, Normalization};
+
+#[test]
+fn keeps_combining_characters() {
+    let normalization = Normalization::default();
+
+    assert_eq!(normalize("São", &normalization), "s\u{e3}o");
+    assert_eq!(normalize("a\u{303}", &normalization), "\u{e3}");
+    assert_eq!(normalize("É", &normalization), "\u{e9}");
+}
@@ -0,0 +1,181 @@
+use std::rc::Rc;
+
+use papagaio::{determine_highest_usage, read_stats, Numbers, Segment, Stats, TrainConfig, UsageCache, END, NUM, START};
+use regex::Regex;
+
+#[test]
+fn no_wraparound_transition() {
+    let stats = Stats::train(vec!["a b c"], &TrainConfig::default());
+
+    assert_eq!(stats.count("a", "b"), 1);
+    assert_eq!(stats.count("b", "c"), 1);
+    assert_eq!(stats.count("c", "a"), 0);
+}
+
+#[test]
+fn parallel_training_matches_sequential() {
+    let lines: Vec<String> = (0..1000)
+        .map(|i| format!("w{} w{} w{} w{}", i % 7, i % 13, i % 5, i % 3))
+        .collect();
+    let sequential = Stats::train(&lines, &TrainConfig::default());
+    let parallel = Stats::train(&lines, &TrainConfig { threads: 4, ..TrainConfig::default() });
+
+    assert_eq!(sequential.contexts().count(), parallel.contexts().count());
+    for (context, next) in sequential.contexts() {
+        for (neigh, number) in next {
+            assert_eq!(parallel.count(context, neigh), number);
+        }
+    }
+}
+
+#[test]
+fn equal_counts_are_ordered_by_word() {
+    let stats = Stats::train(vec!["a c", "a b", "a d d"], &TrainConfig::default());
+    let usage = determine_highest_usage(&stats);
+
+    let words: Vec<&str> = usage["a"].iter().map(|(word, _)| word.as_str()).collect();
+    assert_eq!(words, vec!["b", "c", "d"]);
+    assert!(usage["a"].iter().all(|&(_, number)| number == 1));
+}
+
+#[test]
+fn carriage_returns_are_trimmed() {
+    let config = TrainConfig {
+        token_regex: Some(Regex::new(r"\S+|\r").unwrap()),
+        ..TrainConfig::default()
+    };
+    for config in &[TrainConfig::default(), config] {
+        let stats = read_stats(&b"a b\r\nc d\r\n"[..], config).unwrap();
+
+        for (context, next) in stats.contexts() {
+            assert!(!context.contains('\r'));
+            for (neigh, _) in next {
+                assert!(!neigh.contains('\r'));
+            }
+        }
+        assert_eq!(stats.count("b", END), 1);
+    }
+}
+
+#[test]
+fn byte_order_mark_is_stripped() {
+    let stats = read_stats("\u{feff}hello world\n".as_bytes(), &TrainConfig::default()).unwrap();
+
+    assert_eq!(stats.count(START, "hello"), 1);
+    assert!(stats.contexts().all(|(context, _)| !context.contains('\u{feff}')));
+}
+
+#[test]
+fn byte_order_mark_is_only_stripped_from_the_start() {
+    let stats = read_stats("\u{feff}hello\n\u{feff}world\n".as_bytes(), &TrainConfig::default()).unwrap();
+
+    assert_eq!(stats.count(START, "hello"), 1);
+    assert_eq!(stats.count(START, "\u{feff}world"), 1);
+}
+
+#[test]
+fn numbers_are_collapsed() {
+    let config = TrainConfig { numbers: Numbers::Collapse, ..TrainConfig::default() };
+    let stats = Stats::train(vec!["a 12 b ٣"], &config);
+
+    assert_eq!(stats.count("a", NUM), 1);
+    assert_eq!(stats.count(NUM, "b"), 1);
+    // only ASCII digits make numbers
+    assert_eq!(stats.count("b", "٣"), 1);
+}
+
+#[test]
+fn numbers_are_dropped() {
+    let config = TrainConfig { numbers: Numbers::Drop, ..TrainConfig::default() };
+    let stats = Stats::train(vec!["a 12 b 3"], &config);
+
+    assert_eq!(stats.count("a", "b"), 1);
+    assert_eq!(stats.count("b", END), 1);
+    assert!(stats.get("12").is_none());
+}
+
+#[test]
+fn reading_goes_by_the_config() {
+    let config = TrainConfig { strip_html: true, segment: Segment::Sentence, ..TrainConfig::default() };
+    let input = "\u{feff}<p>one two.</p> three\nfour.\n";
+    let mut stats = Stats::with_config(&config);
+    stats.read(input.as_bytes()).unwrap();
+
+    assert_eq!(stats, read_stats(input.as_bytes(), &config).unwrap());
+    assert_eq!(stats.count(START, "one"), 1);
+    assert_eq!(stats.count("three", "four."), 1);
+
+    let e = Stats::new().read(&b"a\n\xff\n"[..]).unwrap_err();
+    assert!(e.to_string().starts_with("line 2 is not valid UTF-8"), "{}", e);
+}
+
+#[test]
+fn lines_need_not_be_shared_between_threads() {
+    let lines: Vec<Rc<str>> = vec!["a b".into(), "b c".into()];
+    let stats = Stats::train(lines, &TrainConfig { threads: 2, ..TrainConfig::default() });
+
+    assert_eq!(stats.count("b", "c"), 1);
+}
+
+#[test]
+fn html_is_stripped() {
+    let config = TrainConfig { strip_html: true, ..TrainConfig::default() };
+    let stats = read_stats("<p>fish &amp; <b>chips</b></p>\n".as_bytes(), &config).unwrap();
+
+    assert_eq!(stats.count(START, "fish"), 1);
+    assert_eq!(stats.count("fish", "&"), 1);
+    assert_eq!(stats.count("&", "chips"), 1);
+    assert_eq!(stats.count("chips", END), 1);
+}
+
+#[test]
+fn sentences_span_lines() {
+    let config = TrainConfig { segment: Segment::Sentence, ..TrainConfig::default() };
+    let stats = read_stats("the cat\nsat. it was\n\nhappy\n".as_bytes(), &config).unwrap();
+
+    assert_eq!(stats.count("cat", "sat."), 1);
+    assert_eq!(stats.count("sat.", END), 1);
+    assert_eq!(stats.count(START, "it"), 1);
+    assert_eq!(stats.count("was", END), 1);
+    assert_eq!(stats.count(START, "happy"), 1);
+}
+
+#[test]
+fn entropy_of_neighbors() {
+    let stats = Stats::train(["a b", "a c", "b b"], &TrainConfig::default());
+
+    assert_eq!(stats.get("a").unwrap().entropy(), 1.0);
+    assert_eq!(stats.get("c").unwrap().entropy(), 0.0);
+}
+
+#[test]
+fn most_frequent_neighbors_come_first() {
+    let stats = Stats::train(vec!["a b", "a c", "a c"], &TrainConfig::default());
+    let usage = determine_highest_usage(&stats);
+
+    assert_eq!(usage["a"], vec![("c".to_string(), 2), ("b".to_string(), 1)]);
+}
+
+#[test]
+fn usage_cache_follows_the_model() {
+    let config = TrainConfig::default();
+    let mut stats = Stats::train(vec!["a b", "a b", "a c"], &config);
+    let mut cache = UsageCache::new(&stats);
+    assert_eq!(cache.refresh(&stats), 0);
+
+    // "a" changes and "c" is gone
+    stats.prune(2);
+    assert_eq!(cache.refresh(&stats), 1);
+    assert_eq!(cache.usage(), &determine_highest_usage(&stats));
+
+    // the larger model takes the place of the smaller one
+    stats.merge(Stats::train(vec!["x y", "y z", "z a", "a d"], &config));
+    cache.refresh(&stats);
+    assert_eq!(cache.usage(), &determine_highest_usage(&stats));
+
+    for i in 0..2000 {
+        stats.observe(&format!("a {}", i % 3), &config);
+    }
+    assert_eq!(cache.refresh(&stats), 5);
+    assert_eq!(cache.usage(), &determine_highest_usage(&stats));
+}
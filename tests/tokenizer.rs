@@ -0,0 +1,34 @@
+use papagaio::tokenizer::{Punctuation, Tokenizer};
+use papagaio::{read_stats_with, TrainConfig};
+
+#[test]
+fn punctuation_is_split_off() {
+    let sentences = Punctuation.sentences("hello, world! don't");
+
+    assert_eq!(sentences, vec![vec!["hello", ",", "world", "!", "don't"]]);
+}
+
+struct Commas;
+
+impl Tokenizer for Commas {
+    fn sentences<'a>(&self, line: &'a str) -> Vec<Vec<&'a str>> {
+        vec![line.split(',').collect()]
+    }
+}
+
+#[test]
+fn custom_tokenizer() {
+    let stats = read_stats_with(&b"a b,c"[..], &TrainConfig::default(), &Commas).unwrap();
+
+    assert_eq!(stats.count("a\u{a0}b", "c"), 1);
+    assert_eq!(stats.surface("a\u{a0}b"), "a b");
+}
+
+#[test]
+fn spaces_within_tokens_keep_contexts_apart() {
+    let config = TrainConfig { order: 2, ..TrainConfig::default() };
+    let stats = read_stats_with(&b"a b,c,d"[..], &config, &Commas).unwrap();
+
+    assert_eq!(stats.count("<START> a\u{a0}b", "c"), 1);
+    assert_eq!(stats.count("a\u{a0}b c", "d"), 1);
+}
@@ -0,0 +1,86 @@
+use papagaio::{GeneratorConfig, Sampler, Sampling, Stats, TrainConfig, Usage, END, START};
+
+#[test]
+fn self_loop_ends() {
+    let mut stats = Stats::new();
+    stats.update(&["a".to_string()], "a".to_string(), 1);
+
+    let sampler = Sampler::new(&stats, 1.0, false);
+    let words = Usage::new(0.75, &sampler, Some(1)).count();
+    assert!(words > 0 && words <= 31);
+}
+
+#[test]
+fn certain_stop_ends_after_min_words() {
+    let stats = Stats::train(["a b c d e f g h"], &TrainConfig::default());
+    let config = GeneratorConfig { stop_prob: 1.0, min_words: 3, seed: Some(1), ..GeneratorConfig::default() };
+
+    let sampler = Sampler::with_config(&stats, &config);
+    let words: Vec<&str> = Usage::with_config(&sampler, &config).unwrap().collect();
+    assert_eq!(words, ["a", "b", "c"]);
+}
+
+#[test]
+fn unseen_contexts_back_off() {
+    let mut stats = Stats::with_config(&TrainConfig { order: 2, ..TrainConfig::default() });
+    let context = |words: &[&str]| words.iter().map(|word| word.to_string()).collect::<Vec<_>>();
+    stats.update(&context(&[START, START]), "a".to_string(), 1);
+    stats.update(&context(&[START, "a"]), "b".to_string(), 1);
+    stats.update(&context(&["x", "b"]), "c".to_string(), 1);
+    stats.update(&context(&["b", "c"]), END.to_string(), 1);
+
+    let words = |backoff| {
+        let config = GeneratorConfig { backoff, seed: Some(1), ..GeneratorConfig::default() };
+        let sampler = Sampler::with_config(&stats, &config);
+        Usage::with_config(&sampler, &config).unwrap().collect::<Vec<_>>().join(" ")
+    };
+    assert_eq!(words(false), "a b");
+    assert_eq!(words(true), "a b c");
+}
+
+#[test]
+fn attempts_run_out() {
+    let mut stats = Stats::new();
+    stats.update(&["a".to_string()], "a".to_string(), 1);
+    let config = GeneratorConfig { max_attempts: Some(5), seed: Some(1), ..GeneratorConfig::default() };
+
+    let sampler = Sampler::with_config(&stats, &config);
+    let mut usage = Usage::with_config(&sampler, &config).unwrap();
+    assert_eq!(usage.by_ref().count(), 5);
+    assert!(usage.exhausted());
+}
+
+#[test]
+fn sampling_keeps_the_likeliest_words() {
+    let lines = ["a b", "a b", "a b", "a c", "a d"];
+    let stats = Stats::train(lines, &TrainConfig::default());
+
+    for sampling in ["topk:1", "topp:0.5", "topp:0.6"] {
+        let sampling = sampling.parse().unwrap();
+        for seed in 0..20 {
+            let config = GeneratorConfig { sampling, seed: Some(seed), ..GeneratorConfig::default() };
+            let sampler = Sampler::with_config(&stats, &config);
+            let words: Vec<&str> = Usage::with_config(&sampler, &config).unwrap().collect();
+            assert_eq!(words, ["a", "b"], "{:?}", sampling);
+        }
+    }
+}
+
+#[test]
+fn invalid_sampling() {
+    for sampling in ["topk:0", "topp:0", "topp:1.5", "topk", "greedy"] {
+        assert!(sampling.parse::<Sampling>().is_err(), "{}", sampling);
+    }
+}
+
+#[test]
+fn starts_without_a() {
+    let mut stats = Stats::new();
+    stats.update(&["b".to_string()], "c".to_string(), 2);
+    stats.update(&["c".to_string()], END.to_string(), 1);
+
+    let sampler = Sampler::new(&stats, 1.0, false);
+    let words: Vec<&str> = Usage::new(0.75, &sampler, Some(1)).collect();
+    assert!(!words.is_empty());
+    assert!(words.iter().all(|word| stats.get(word).is_some()));
+}